@@ -272,6 +272,9 @@ pub const KATA_ANNO_CFG_HYPERVISOR_VIRTIO_FS_CACHE: &str =
 /// A sandbox annotation to specify the DAX cache size in MiB.
 pub const KATA_ANNO_CFG_HYPERVISOR_VIRTIO_FS_CACHE_SIZE: &str =
     "io.katacontainers.config.hypervisor.virtio_fs_cache_size";
+/// A sandbox annotation to specify the thread pool size of the virtio-fs backend.
+pub const KATA_ANNO_CFG_HYPERVISOR_VIRTIO_FS_THREAD_POOL_SIZE: &str =
+    "io.katacontainers.config.hypervisor.virtio_fs_thread_pool_size";
 /// A sandbox annotation to pass options to virtiofsd daemon.
 pub const KATA_ANNO_CFG_HYPERVISOR_VIRTIO_FS_EXTRA_ARGS: &str =
     "io.katacontainers.config.hypervisor.virtio_fs_extra_args";
@@ -830,6 +833,27 @@ impl Annotation {
                             }
                         }
                     }
+                    KATA_ANNO_CFG_HYPERVISOR_VIRTIO_FS_THREAD_POOL_SIZE => {
+                        match self.get_value::<u32>(key) {
+                            Ok(r) => {
+                                let size = r.unwrap_or_default();
+                                if size > u16::MAX as u32 {
+                                    return Err(io::Error::new(
+                                        io::ErrorKind::InvalidData,
+                                        format!(
+                                            "Virtio-fs thread pool size specified in annotation {} is more than maximum limitation {}",
+                                            size,
+                                            u16::MAX
+                                        ),
+                                    ));
+                                }
+                                hv.shared_fs.virtio_fs_thread_pool_size = size;
+                            }
+                            Err(_e) => {
+                                return Err(u32_err);
+                            }
+                        }
+                    }
                     KATA_ANNO_CFG_HYPERVISOR_VIRTIO_FS_EXTRA_ARGS => {
                         let args: Vec<String> =
                             value.to_string().split(',').map(str::to_string).collect();
@@ -29,6 +29,7 @@ pub const DEFAULT_RUNTIME_NAME: &str = RUNTIME_NAME_VIRTCONTAINER;
 pub const DEFAULT_HYPERVISOR: &str = HYPERVISOR_NAME_DRAGONBALL;
 
 pub const DEFAULT_INTERNETWORKING_MODEL: &str = "tcfilter";
+pub const DEFAULT_MAX_SHM_SIZE_MB: u32 = 4 * 1024;
 
 pub const DEFAULT_BLOCK_DEVICE_TYPE: &str = "virtio-blk";
 pub const DEFAULT_VHOST_USER_STORE_PATH: &str = "/var/run/vhost-user";
@@ -783,6 +783,18 @@ pub struct SharedFsInfo {
     #[serde(default)]
     pub virtio_fs_is_dax: bool,
 
+    /// Size of the worker thread pool of the virtio-fs backend, 0 means the backend default.
+    #[serde(default)]
+    pub virtio_fs_thread_pool_size: u32,
+
+    /// Bandwidth limit of the virtio-fs backend in bytes/sec, 0 means unlimited.
+    #[serde(default)]
+    pub virtio_fs_rate_limiter_max_rate: u64,
+
+    /// Operation rate limit of the virtio-fs backend in ops/sec, 0 means unlimited.
+    #[serde(default)]
+    pub virtio_fs_rate_limiter_max_ops: u64,
+
     /// This is the msize used for 9p shares. It is the number of bytes used for 9p packet payload.
     #[serde(default)]
     pub msize_9p: u32,
@@ -878,6 +890,12 @@ impl SharedFsInfo {
                 &self.virtio_fs_cache_size
             ));
         }
+        if self.virtio_fs_thread_pool_size > u16::MAX as u32 {
+            return Err(eother!(
+                "Invalid virtio-fs thread pool size: {}",
+                self.virtio_fs_thread_pool_size
+            ));
+        }
         Ok(())
     }
 }
@@ -1084,6 +1102,40 @@ mod tests {
         assert!(get_hypervisor_plugin("dragonball2").is_none());
     }
 
+    #[test]
+    fn test_shared_fs_rate_limiter() {
+        let shared_fs: SharedFsInfo = toml::from_str(
+            r#"
+            shared_fs = "inline-virtio-fs"
+            virtio_fs_rate_limiter_max_rate = 1048576
+            virtio_fs_rate_limiter_max_ops = 1000
+            "#,
+        )
+        .unwrap();
+        assert_eq!(shared_fs.virtio_fs_rate_limiter_max_rate, 1048576);
+        assert_eq!(shared_fs.virtio_fs_rate_limiter_max_ops, 1000);
+
+        let shared_fs: SharedFsInfo = toml::from_str(r#"shared_fs = "virtio-fs""#).unwrap();
+        assert_eq!(shared_fs.virtio_fs_rate_limiter_max_rate, 0);
+        assert_eq!(shared_fs.virtio_fs_rate_limiter_max_ops, 0);
+    }
+
+    #[test]
+    fn test_shared_fs_thread_pool_size() {
+        let mut shared_fs: SharedFsInfo = toml::from_str(
+            r#"
+            shared_fs = "inline-virtio-fs"
+            virtio_fs_cache = "none"
+            virtio_fs_thread_pool_size = 65535
+            "#,
+        )
+        .unwrap();
+        assert!(shared_fs.validate().is_ok());
+
+        shared_fs.virtio_fs_thread_pool_size = 65536;
+        assert!(shared_fs.validate().is_err());
+    }
+
     #[test]
     fn test_add_kernel_params() {
         let mut boot_info = BootInfo {
@@ -126,6 +126,37 @@ pub struct Runtime {
     #[serde(default)]
     pub vfio_mode: String,
 
+    /// List of container mount destinations which are not handled as volumes.
+    ///
+    /// The matching mounts are dropped from the container spec, which is useful for mounts
+    /// set up by other means inside the guest, e.g. secrets provided by a guest service.
+    #[serde(default)]
+    pub skip_volume_destinations: Vec<String>,
+
+    /// Maximum size in MiB of the `/dev/shm` tmpfs a container may request.
+    ///
+    /// The size is taken from the `size=` option of the container's `/dev/shm` mount, requests
+    /// above the limit are rejected.
+    #[serde(default)]
+    pub max_shm_size_mb: u32,
+
+    /// Guest directory the shared filesystem volumes are remapped under.
+    ///
+    /// If set, each volume shared through the shared filesystem is bind mounted by the agent
+    /// to `<prefix>/<container id>/<destination>` in the guest, and the container mounts it
+    /// from there to its original destination. It must be an absolute path, an empty value
+    /// leaves the volumes at their location in the shared directory.
+    #[serde(default)]
+    pub share_fs_volume_guest_prefix: String,
+
+    /// Give containers a writable overlay on top of the shared rootfs.
+    ///
+    /// If enabled, the rootfs is shared read-only and used as the overlay lower directory, the
+    /// agent creates the upper and work directories on the guest tmpfs. Containers with a
+    /// read-only rootfs are left untouched.
+    #[serde(default)]
+    pub share_fs_rootfs_overlay: bool,
+
     /// Vendor customized runtime configuration.
     #[serde(default, flatten)]
     pub vendor: RuntimeVendor,
@@ -137,6 +168,9 @@ impl ConfigOps for Runtime {
         if conf.runtime.internetworking_model.is_empty() {
             conf.runtime.internetworking_model = default::DEFAULT_INTERNETWORKING_MODEL.to_owned();
         }
+        if conf.runtime.max_shm_size_mb == 0 {
+            conf.runtime.max_shm_size_mb = default::DEFAULT_MAX_SHM_SIZE_MB;
+        }
 
         for bind in conf.runtime.sandbox_bind_mounts.iter_mut() {
             resolve_path!(*bind, "sandbox bind mount `{}` is invalid: {}")?;
@@ -172,6 +206,14 @@ impl ConfigOps for Runtime {
             validate_path!(*bind, "sandbox bind mount `{}` is invalid: {}")?;
         }
 
+        let prefix = &conf.runtime.share_fs_volume_guest_prefix;
+        if !prefix.is_empty() && !Path::new(prefix).is_absolute() {
+            return Err(eother!(
+                "Invalid share_fs_volume_guest_prefix `{}` in configuration file, it must be an absolute path",
+                prefix
+            ));
+        }
+
         Ok(())
     }
 }
@@ -253,6 +295,66 @@ vfio_mode = "guest_kernel"
         config.validate().unwrap_err();
     }
 
+    #[test]
+    fn test_skip_volume_destinations() {
+        let content = r#"
+[runtime]
+skip_volume_destinations = ["/etc/secret", "/run/guest-service"]
+"#;
+        let config: TomlConfig = TomlConfig::load(content).unwrap();
+        config.validate().unwrap();
+        assert_eq!(
+            config.runtime.skip_volume_destinations,
+            vec!["/etc/secret", "/run/guest-service"]
+        );
+
+        let config: TomlConfig = TomlConfig::load("[runtime]").unwrap();
+        assert!(config.runtime.skip_volume_destinations.is_empty());
+    }
+
+    #[test]
+    fn test_max_shm_size() {
+        let mut config: TomlConfig = TomlConfig::load("[runtime]").unwrap();
+        Runtime::adjust_config(&mut config).unwrap();
+        assert_eq!(
+            config.runtime.max_shm_size_mb,
+            default::DEFAULT_MAX_SHM_SIZE_MB
+        );
+
+        let content = r#"
+[runtime]
+max_shm_size_mb = 512
+"#;
+        let mut config: TomlConfig = TomlConfig::load(content).unwrap();
+        Runtime::adjust_config(&mut config).unwrap();
+        assert_eq!(config.runtime.max_shm_size_mb, 512);
+    }
+
+    #[test]
+    fn test_share_fs_volume_guest_prefix() {
+        let config: TomlConfig = TomlConfig::load("[runtime]").unwrap();
+        config.validate().unwrap();
+        assert!(config.runtime.share_fs_volume_guest_prefix.is_empty());
+
+        let content = r#"
+[runtime]
+share_fs_volume_guest_prefix = "/run/kata/volumes"
+"#;
+        let config: TomlConfig = TomlConfig::load(content).unwrap();
+        config.validate().unwrap();
+        assert_eq!(
+            config.runtime.share_fs_volume_guest_prefix,
+            "/run/kata/volumes"
+        );
+
+        let content = r#"
+[runtime]
+share_fs_volume_guest_prefix = "run/kata/volumes"
+"#;
+        let config: TomlConfig = TomlConfig::load(content).unwrap();
+        config.validate().unwrap_err();
+    }
+
     #[test]
     fn test_config() {
         let content = r#"
@@ -17,8 +17,8 @@ mod tests {
         KATA_ANNO_CFG_HYPERVISOR_MEMORY_PREALLOC, KATA_ANNO_CFG_HYPERVISOR_MEMORY_SLOTS,
         KATA_ANNO_CFG_HYPERVISOR_PATH, KATA_ANNO_CFG_HYPERVISOR_VHOSTUSER_STORE_PATH,
         KATA_ANNO_CFG_HYPERVISOR_VIRTIO_FS_DAEMON, KATA_ANNO_CFG_HYPERVISOR_VIRTIO_FS_EXTRA_ARGS,
-        KATA_ANNO_CFG_HYPERVISOR_VIRTIO_MEM, KATA_ANNO_CFG_KERNEL_MODULES,
-        KATA_ANNO_CFG_RUNTIME_NAME,
+        KATA_ANNO_CFG_HYPERVISOR_VIRTIO_FS_THREAD_POOL_SIZE, KATA_ANNO_CFG_HYPERVISOR_VIRTIO_MEM,
+        KATA_ANNO_CFG_KERNEL_MODULES, KATA_ANNO_CFG_RUNTIME_NAME,
     };
     use kata_types::config::KataConfig;
     use kata_types::config::{QemuConfig, TomlConfig};
@@ -157,6 +157,10 @@ mod tests {
             KATA_ANNO_CFG_HYPERVISOR_VIRTIO_FS_EXTRA_ARGS.to_string(),
             "rr,dg,er".to_string(),
         );
+        anno_hash.insert(
+            KATA_ANNO_CFG_HYPERVISOR_VIRTIO_FS_THREAD_POOL_SIZE.to_string(),
+            "16".to_string(),
+        );
         anno_hash.insert(
             KATA_ANNO_CFG_HYPERVISOR_VIRTIO_MEM.to_string(),
             "false".to_string(),
@@ -213,6 +217,7 @@ mod tests {
             assert_eq!(hv.shared_fs.virtio_fs_extra_args[5], "rr");
             assert_eq!(hv.shared_fs.virtio_fs_extra_args[6], "dg");
             assert_eq!(hv.shared_fs.virtio_fs_extra_args[7], "er");
+            assert_eq!(hv.shared_fs.virtio_fs_thread_pool_size, 16);
             assert!(!hv.memory_info.enable_virtio_mem);
             assert_eq!(hv.shared_fs.virtio_fs_daemon, "./virtio_fs");
         }
@@ -388,6 +393,26 @@ mod tests {
         assert!(anno.update_config_by_annotation(&mut config).is_err());
     }
 
+    #[test]
+    fn test_fail_to_change_virtio_fs_thread_pool_size_because_more_than_max() {
+        let content = include_str!("texture/configuration-anno-0.toml");
+        let config = TomlConfig::load(content).unwrap();
+        KataConfig::set_active_config(Some(config), "qemu", "agent0");
+
+        let qemu = QemuConfig::new();
+        qemu.register();
+
+        let mut anno_hash = HashMap::new();
+        anno_hash.insert(
+            KATA_ANNO_CFG_HYPERVISOR_VIRTIO_FS_THREAD_POOL_SIZE.to_string(),
+            "65536".to_string(),
+        );
+        let anno = Annotation::new(anno_hash);
+        let mut config = TomlConfig::load(content).unwrap();
+
+        assert!(anno.update_config_by_annotation(&mut config).is_err());
+    }
+
     #[test]
     fn test_fail_to_change_default_memory_because_less_than_min_memory_size() {
         let content = include_str!("texture/configuration-anno-0.toml");
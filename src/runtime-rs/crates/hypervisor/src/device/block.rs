@@ -4,7 +4,40 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-#[derive(Debug)]
+/// BlockCacheMode: how the host page cache is used for the backing file of a drive.
+///
+/// The modes follow the qemu ones. A VMM which can't open the backing file with
+/// O_DSYNC has to reject the sync modes rather than fall back to a non-sync one,
+/// e.g. dragonball only supports `writeback` and `none`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockCacheMode {
+    /// Go through the host page cache, writes are durable after a guest flush.
+    WriteBack,
+    /// Go through the host page cache, every write is durable on completion (O_DSYNC).
+    WriteThrough,
+    /// Bypass the host page cache (O_DIRECT), writes are durable after a guest flush.
+    None,
+    /// Bypass the host page cache, every write is durable on completion
+    /// (O_DIRECT | O_DSYNC).
+    DirectSync,
+}
+
+impl BlockCacheMode {
+    /// Whether the backing file should bypass the host page cache (O_DIRECT).
+    pub fn is_direct(&self) -> bool {
+        matches!(self, BlockCacheMode::None | BlockCacheMode::DirectSync)
+    }
+
+    /// Whether every write has to be durable on completion (O_DSYNC).
+    pub fn is_sync(&self) -> bool {
+        matches!(
+            self,
+            BlockCacheMode::WriteThrough | BlockCacheMode::DirectSync
+        )
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct BlockConfig {
     /// Unique identifier of the drive.
     pub id: String,
@@ -21,4 +54,29 @@ pub struct BlockConfig {
 
     /// device index
     pub index: u64,
+
+    /// Cache mode of the drive, `None` means the VMM default is used.
+    pub cache_mode: Option<BlockCacheMode>,
+
+    /// Number of virtqueues of the drive, `None` means the VMM default is used.
+    pub num_queues: Option<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_cache_mode() {
+        let cases = [
+            (BlockCacheMode::WriteBack, false, false),
+            (BlockCacheMode::WriteThrough, false, true),
+            (BlockCacheMode::None, true, false),
+            (BlockCacheMode::DirectSync, true, true),
+        ];
+        for (mode, direct, sync) in cases {
+            assert_eq!(mode.is_direct(), direct);
+            assert_eq!(mode.is_sync(), sync);
+        }
+    }
 }
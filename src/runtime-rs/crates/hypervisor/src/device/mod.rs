@@ -5,7 +5,7 @@
 //
 
 mod block;
-pub use block::BlockConfig;
+pub use block::{BlockCacheMode, BlockConfig};
 mod network;
 pub use network::{Address, NetworkConfig};
 mod share_fs_device;
@@ -13,7 +13,9 @@ pub use share_fs_device::ShareFsDeviceConfig;
 mod vfio;
 pub use vfio::{bind_device_to_host, bind_device_to_vfio, VfioBusMode, VfioConfig};
 mod share_fs_mount;
-pub use share_fs_mount::{ShareFsMountConfig, ShareFsMountType, ShareFsOperation};
+pub use share_fs_mount::{
+    ShareFsMountConfig, ShareFsMountType, ShareFsOperation, ShareFsRateLimit,
+};
 mod vsock;
 pub use vsock::VsockConfig;
 
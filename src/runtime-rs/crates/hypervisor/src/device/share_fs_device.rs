@@ -24,4 +24,7 @@ pub struct ShareFsDeviceConfig {
 
     /// queue_num: queue number
     pub queue_num: u64,
+
+    /// thread_pool_size: size of the worker thread pool, 0 means the backend default
+    pub thread_pool_size: u32,
 }
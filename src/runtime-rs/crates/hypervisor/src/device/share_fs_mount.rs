@@ -17,6 +17,22 @@ pub enum ShareFsMountType {
     RAFS,
 }
 
+/// ShareFsRateLimit: IO limits enforced by the share fs backend, 0 means unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShareFsRateLimit {
+    /// bandwidth: bytes per second
+    pub bandwidth: u64,
+
+    /// ops: operations per second
+    pub ops: u64,
+}
+
+impl ShareFsRateLimit {
+    pub fn is_unlimited(&self) -> bool {
+        self.bandwidth == 0 && self.ops == 0
+    }
+}
+
 /// ShareFsMountConfig: share fs mount config
 #[derive(Debug)]
 pub struct ShareFsMountConfig {
@@ -40,4 +56,7 @@ pub struct ShareFsMountConfig {
 
     /// prefetch_list_path: path to file that contains file lists that should be prefetched by rafs
     pub prefetch_list_path: Option<String>,
+
+    /// rate_limit: IO limits of the share fs device, `None` means no throttling
+    pub rate_limit: Option<ShareFsRateLimit>,
 }
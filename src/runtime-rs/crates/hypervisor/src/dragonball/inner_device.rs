@@ -4,25 +4,31 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use std::path::PathBuf;
+use std::{convert::TryFrom, path::PathBuf};
 
 use anyhow::{anyhow, Context, Result};
 use dbs_utils::net::MacAddr;
-use dragonball::api::v1::{
-    BlockDeviceConfigInfo, FsDeviceConfigInfo, FsMountConfigInfo, VirtioNetDeviceConfigInfo,
-    VsockDeviceConfigInfo,
+use dragonball::{
+    api::v1::{
+        BlockDeviceConfigInfo, FsDeviceConfigInfo, FsDeviceConfigUpdateInfo, FsMountConfigInfo,
+        VirtioNetDeviceConfigInfo, VsockDeviceConfigInfo,
+    },
+    config_manager::{RateLimiterConfigInfo, TokenBucketConfigInfo},
 };
 
 use super::DragonballInner;
 use crate::{
-    device::Device, NetworkConfig, ShareFsDeviceConfig, ShareFsMountConfig, ShareFsMountType,
-    ShareFsOperation, VmmState, VsockConfig,
+    device::Device, BlockConfig, NetworkConfig, ShareFsDeviceConfig, ShareFsMountConfig,
+    ShareFsMountType, ShareFsOperation, ShareFsRateLimit, VmmState, VsockConfig,
 };
 
 const MB_TO_B: u32 = 1024 * 1024;
 const DEFAULT_VIRTIO_FS_NUM_QUEUES: i32 = 1;
 const DEFAULT_VIRTIO_FS_QUEUE_SIZE: i32 = 1024;
 
+// Token buckets of the share fs rate limiter are refilled once a second.
+const FS_RATE_LIMITER_REFILL_TIME_MS: u64 = 1000;
+
 const VIRTIO_FS: &str = "virtio-fs";
 const INLINE_VIRTIO_FS: &str = "inline-virtio-fs";
 
@@ -30,6 +36,22 @@ pub(crate) fn drive_index_to_id(index: u64) -> String {
     format!("drive_{}", index)
 }
 
+// A zero sized token bucket is disabled, so a 0 limit means unlimited.
+fn fs_rate_limiter_update_info(tag: &str, limit: &ShareFsRateLimit) -> FsDeviceConfigUpdateInfo {
+    let bucket = |size| TokenBucketConfigInfo {
+        size,
+        one_time_burst: 0,
+        refill_time: FS_RATE_LIMITER_REFILL_TIME_MS,
+    };
+    FsDeviceConfigUpdateInfo {
+        tag: tag.to_string(),
+        rate_limiter: Some(RateLimiterConfigInfo {
+            bandwidth: bucket(limit.bandwidth),
+            ops: bucket(limit.ops),
+        }),
+    }
+}
+
 impl DragonballInner {
     pub(crate) async fn add_device(&mut self, device: Device) -> Result<()> {
         if self.state == VmmState::NotReady {
@@ -48,14 +70,7 @@ impl DragonballInner {
             Device::Vfio(_config) => {
                 todo!()
             }
-            Device::Block(config) => self
-                .add_block_device(
-                    config.path_on_host.as_str(),
-                    config.id.as_str(),
-                    config.is_readonly,
-                    config.no_drop,
-                )
-                .context("add block device"),
+            Device::Block(config) => self.add_block_device(&config).context("add block device"),
             Device::Vsock(config) => self.add_vsock(&config).context("add vsock"),
             Device::ShareFsDevice(config) => self
                 .add_share_fs_device(&config)
@@ -82,29 +97,51 @@ impl DragonballInner {
         }
     }
 
-    fn add_block_device(
-        &mut self,
-        path: &str,
-        id: &str,
-        read_only: bool,
-        no_drop: bool,
-    ) -> Result<()> {
-        let jailed_drive = self.get_resource(path, id).context("get resource")?;
-        self.cached_block_devices.insert(id.to_string());
-
-        let blk_cfg = BlockDeviceConfigInfo {
-            drive_id: id.to_string(),
-            path_on_host: PathBuf::from(jailed_drive),
-            is_direct: self.config.blockdev_info.block_device_cache_direct,
-            no_drop,
-            is_read_only: read_only,
-            ..Default::default()
-        };
+    fn add_block_device(&mut self, config: &BlockConfig) -> Result<()> {
+        let jailed_drive = self
+            .get_resource(&config.path_on_host, &config.id)
+            .context("get resource")?;
+        self.cached_block_devices.insert(config.id.clone());
+
+        let blk_cfg = self.block_device_config_info(config, jailed_drive)?;
         self.vmm_instance
             .insert_block_device(blk_cfg)
             .context("insert block device")
     }
 
+    fn block_device_config_info(
+        &self,
+        config: &BlockConfig,
+        jailed_drive: String,
+    ) -> Result<BlockDeviceConfigInfo> {
+        // the cache mode of the drive takes precedence over the hypervisor wide setting,
+        // dragonball can't open the backing file with O_DSYNC, so the sync modes aren't
+        // supported rather than silently losing their durability
+        let is_direct = match config.cache_mode {
+            None => self.config.blockdev_info.block_device_cache_direct,
+            Some(mode) if mode.is_sync() => {
+                return Err(anyhow!(
+                    "block cache mode {:?} of {} is not supported by dragonball",
+                    mode,
+                    config.id
+                ))
+            }
+            Some(mode) => mode.is_direct(),
+        };
+
+        Ok(BlockDeviceConfigInfo {
+            drive_id: config.id.clone(),
+            path_on_host: PathBuf::from(jailed_drive),
+            is_direct,
+            no_drop: config.no_drop,
+            is_read_only: config.is_readonly,
+            num_queues: config
+                .num_queues
+                .unwrap_or_else(BlockDeviceConfigInfo::default_num_queues),
+            ..Default::default()
+        })
+    }
+
     fn remove_block_drive(&mut self, id: &str) -> Result<()> {
         self.vmm_instance
             .remove_block_device(id)
@@ -208,7 +245,15 @@ impl DragonballInner {
     }
 
     fn add_share_fs_device(&self, config: &ShareFsDeviceConfig) -> Result<()> {
-        let mut fs_cfg = FsDeviceConfigInfo {
+        let mut fs_cfg = self.share_fs_device_config_info(config)?;
+        self.do_add_fs_device(&config.fs_type, &mut fs_cfg)
+    }
+
+    fn share_fs_device_config_info(
+        &self,
+        config: &ShareFsDeviceConfig,
+    ) -> Result<FsDeviceConfigInfo> {
+        Ok(FsDeviceConfigInfo {
             sock_path: config.sock_path.clone(),
             tag: config.mount_tag.clone(),
             num_queues: if config.queue_num > 0 {
@@ -223,9 +268,10 @@ impl DragonballInner {
             },
             cache_size: (self.config.shared_fs.virtio_fs_cache_size as u64)
                 .saturating_mul(MB_TO_B as u64),
+            thread_pool_size: u16::try_from(config.thread_pool_size)
+                .with_context(|| format!("invalid thread pool size {}", config.thread_pool_size))?,
             ..Default::default()
-        };
-        self.do_add_fs_device(&config.fs_type, &mut fs_cfg)
+        })
     }
 
     fn do_add_fs_device(&self, fs_type: &str, fs_cfg: &mut FsDeviceConfigInfo) -> Result<()> {
@@ -278,15 +324,107 @@ impl DragonballInner {
                 config.mount_point.clone(),
                 e
             )
-        })
+        })?;
+
+        // the limits apply to the whole device, which is shared by all its mounts
+        match config.rate_limit {
+            Some(limit) if !limit.is_unlimited() => self
+                .vmm_instance
+                .update_fs(&fs_rate_limiter_update_info(&config.tag, &limit))
+                .with_context(|| format!("set rate limit {:?} of {}", limit, config.tag)),
+            _ => Ok(()),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use dragonball::api::v1::FsDeviceConfigInfo;
+    use dragonball::api::v1::{BlockDeviceConfigInfo, FsDeviceConfigInfo};
 
+    use super::fs_rate_limiter_update_info;
     use crate::dragonball::DragonballInner;
+    use crate::{BlockCacheMode, BlockConfig, ShareFsDeviceConfig, ShareFsRateLimit};
+
+    #[test]
+    fn test_block_device_config_info_cache_mode() {
+        let mut dragonball = DragonballInner::new();
+        dragonball.config.blockdev_info.block_device_cache_direct = true;
+
+        let mut config = BlockConfig {
+            id: "drive_0".to_string(),
+            ..Default::default()
+        };
+        let blk_cfg = dragonball
+            .block_device_config_info(&config, "/dev/foo".to_string())
+            .unwrap();
+        assert!(blk_cfg.is_direct);
+
+        config.cache_mode = Some(BlockCacheMode::WriteBack);
+        let blk_cfg = dragonball
+            .block_device_config_info(&config, "/dev/foo".to_string())
+            .unwrap();
+        assert!(!blk_cfg.is_direct);
+
+        dragonball.config.blockdev_info.block_device_cache_direct = false;
+        config.cache_mode = Some(BlockCacheMode::None);
+        let blk_cfg = dragonball
+            .block_device_config_info(&config, "/dev/foo".to_string())
+            .unwrap();
+        assert!(blk_cfg.is_direct);
+
+        for mode in [BlockCacheMode::WriteThrough, BlockCacheMode::DirectSync] {
+            config.cache_mode = Some(mode);
+            assert!(dragonball
+                .block_device_config_info(&config, "/dev/foo".to_string())
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_block_device_config_info_num_queues() {
+        let dragonball = DragonballInner::new();
+
+        let mut config = BlockConfig {
+            id: "drive_0".to_string(),
+            ..Default::default()
+        };
+        let blk_cfg = dragonball
+            .block_device_config_info(&config, "/dev/foo".to_string())
+            .unwrap();
+        assert_eq!(
+            blk_cfg.num_queues,
+            BlockDeviceConfigInfo::default_num_queues()
+        );
+
+        config.num_queues = Some(8);
+        let blk_cfg = dragonball
+            .block_device_config_info(&config, "/dev/foo".to_string())
+            .unwrap();
+        assert_eq!(blk_cfg.num_queues, 8);
+    }
+
+    #[test]
+    fn test_share_fs_device_config_info_thread_pool_size() {
+        let dragonball = DragonballInner::new();
+        let mut config = ShareFsDeviceConfig {
+            fs_type: "inline-virtio-fs".to_string(),
+            sock_path: "".to_string(),
+            mount_tag: "kataShared".to_string(),
+            host_path: "/run/kata-containers/shared/sandboxes/sid/ro".to_string(),
+            queue_size: 0,
+            queue_num: 0,
+            thread_pool_size: 0,
+        };
+        let fs_cfg = dragonball.share_fs_device_config_info(&config).unwrap();
+        assert_eq!(fs_cfg.thread_pool_size, 0);
+
+        config.thread_pool_size = 8;
+        let fs_cfg = dragonball.share_fs_device_config_info(&config).unwrap();
+        assert_eq!(fs_cfg.thread_pool_size, 8);
+
+        config.thread_pool_size = u16::MAX as u32 + 1;
+        assert!(dragonball.share_fs_device_config_info(&config).is_err());
+    }
 
     #[test]
     fn test_parse_inline_virtiofs_args() {
@@ -313,4 +451,18 @@ mod tests {
         assert!(fs_cfg.drop_sys_resource);
         assert!(fs_cfg.thread_pool_size == 128);
     }
+
+    #[test]
+    fn test_fs_rate_limiter_update_info() {
+        let limit = ShareFsRateLimit {
+            bandwidth: 1 << 20,
+            ops: 0,
+        };
+        let info = fs_rate_limiter_update_info("kataShared", &limit);
+        assert_eq!(info.tag, "kataShared");
+        let rate_limiter = info.rate_limiter.unwrap();
+        assert_eq!(rate_limiter.bandwidth.size, 1 << 20);
+        assert_eq!(rate_limiter.bandwidth.refill_time, 1000);
+        assert_eq!(rate_limiter.ops.size, 0);
+    }
 }
@@ -17,9 +17,9 @@ use std::{
 use anyhow::{anyhow, Context, Result};
 use dragonball::{
     api::v1::{
-        BlockDeviceConfigInfo, BootSourceConfig, FsDeviceConfigInfo, FsMountConfigInfo,
-        InstanceInfo, InstanceState, VirtioNetDeviceConfigInfo, VmmAction, VmmActionError, VmmData,
-        VmmRequest, VmmResponse, VmmService, VsockDeviceConfigInfo,
+        BlockDeviceConfigInfo, BootSourceConfig, FsDeviceConfigInfo, FsDeviceConfigUpdateInfo,
+        FsMountConfigInfo, InstanceInfo, InstanceState, VirtioNetDeviceConfigInfo, VmmAction,
+        VmmActionError, VmmData, VmmRequest, VmmResponse, VmmService, VsockDeviceConfigInfo,
     },
     vm::VmConfigInfo,
     Vmm,
@@ -233,6 +233,12 @@ impl VmmInstance {
         Ok(())
     }
 
+    pub fn update_fs(&self, cfg: &FsDeviceConfigUpdateInfo) -> Result<()> {
+        self.handle_request(Request::Sync(VmmAction::UpdateFsDevice(cfg.clone())))
+            .with_context(|| format!("Failed to update fs device {:?}", cfg))?;
+        Ok(())
+    }
+
     pub fn pause(&self) -> Result<()> {
         todo!()
     }
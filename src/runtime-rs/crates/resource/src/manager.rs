@@ -50,6 +50,11 @@ impl ResourceManager {
         inner.config()
     }
 
+    pub async fn fs_sharing_supported(&self) -> Result<bool> {
+        let inner = self.inner.read().await;
+        inner.fs_sharing_supported().await
+    }
+
     pub async fn prepare_before_start_vm(&self, device_configs: Vec<ResourceConfig>) -> Result<()> {
         let mut inner = self.inner.write().await;
         inner.prepare_before_start_vm(device_configs).await
@@ -68,11 +73,14 @@ impl ResourceManager {
     pub async fn handler_rootfs(
         &self,
         cid: &str,
+        root: &oci::Root,
         bundle_path: &str,
         rootfs_mounts: &[Mount],
     ) -> Result<Arc<dyn Rootfs>> {
         let inner = self.inner.read().await;
-        inner.handler_rootfs(cid, bundle_path, rootfs_mounts).await
+        inner
+            .handler_rootfs(cid, root, bundle_path, rootfs_mounts)
+            .await
     }
 
     pub async fn handler_volumes(
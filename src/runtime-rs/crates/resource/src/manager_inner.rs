@@ -19,7 +19,7 @@ use persist::sandbox_persist::Persist;
 use crate::{
     cgroups::{CgroupArgs, CgroupsResource},
     manager::ManagerArgs,
-    network::{self, Network},
+    network::{self, Network, NetworkConfig},
     rootfs::{RootFsResource, Rootfs},
     share_fs::{self, ShareFs},
     volume::{Volume, VolumeResource},
@@ -47,6 +47,8 @@ impl ResourceManagerInner {
         toml_config: Arc<TomlConfig>,
     ) -> Result<Self> {
         let cgroups_resource = CgroupsResource::new(sid, &toml_config)?;
+        let rootfs_resource = RootFsResource::new(&toml_config);
+        let volume_resource = VolumeResource::new(&toml_config);
         Ok(Self {
             sid: sid.to_string(),
             toml_config,
@@ -54,8 +56,8 @@ impl ResourceManagerInner {
             hypervisor,
             network: None,
             share_fs: None,
-            rootfs_resource: RootFsResource::new(),
-            volume_resource: VolumeResource::new(),
+            rootfs_resource,
+            volume_resource,
             cgroups_resource,
         })
     }
@@ -64,6 +66,10 @@ impl ResourceManagerInner {
         self.toml_config.clone()
     }
 
+    pub async fn fs_sharing_supported(&self) -> Result<bool> {
+        share_fs::is_fs_sharing_supported(self.hypervisor.as_ref()).await
+    }
+
     pub async fn prepare_before_start_vm(
         &mut self,
         device_configs: Vec<ResourceConfig>,
@@ -71,12 +77,7 @@ impl ResourceManagerInner {
         for dc in device_configs {
             match dc {
                 ResourceConfig::ShareFs(c) => {
-                    self.share_fs = if self
-                        .hypervisor
-                        .capabilities()
-                        .await?
-                        .is_fs_sharing_supported()
-                    {
+                    self.share_fs = if self.fs_sharing_supported().await? {
                         let share_fs = share_fs::new(&self.sid, &c).context("new share fs")?;
                         share_fs
                             .setup_device_before_start_vm(self.hypervisor.as_ref())
@@ -88,11 +89,9 @@ impl ResourceManagerInner {
                     };
                 }
                 ResourceConfig::Network(c) => {
-                    let d = network::new(&c).await.context("new network")?;
-                    d.setup(self.hypervisor.as_ref())
-                        .await
-                        .context("setup network")?;
-                    self.network = Some(d)
+                    let network = self.setup_network(&c).await;
+                    let network = teardown_share_fs_on_error(&mut self.share_fs, network).await?;
+                    self.network = Some(network)
                 }
             };
         }
@@ -100,6 +99,14 @@ impl ResourceManagerInner {
         Ok(())
     }
 
+    async fn setup_network(&self, c: &NetworkConfig) -> Result<Arc<dyn Network>> {
+        let d = network::new(c).await.context("new network")?;
+        d.setup(self.hypervisor.as_ref())
+            .await
+            .context("setup network")?;
+        Ok(d)
+    }
+
     async fn handle_interfaces(&self, network: &dyn Network) -> Result<()> {
         for i in network.interfaces().await.context("get interfaces")? {
             // update interface
@@ -174,11 +181,12 @@ impl ResourceManagerInner {
     pub async fn handler_rootfs(
         &self,
         cid: &str,
+        root: &oci::Root,
         bundle_path: &str,
         rootfs_mounts: &[Mount],
     ) -> Result<Arc<dyn Rootfs>> {
         self.rootfs_resource
-            .handler_rootfs(&self.share_fs, cid, bundle_path, rootfs_mounts)
+            .handler_rootfs(&self.share_fs, cid, root, bundle_path, rootfs_mounts)
             .await
     }
 
@@ -237,6 +245,8 @@ impl Persist for ResourceManagerInner {
         resource_args: Self::ConstructorArgs,
         resource_state: Self::State,
     ) -> Result<Self> {
+        let rootfs_resource = RootFsResource::new(&resource_args.config);
+        let volume_resource = VolumeResource::new(&resource_args.config);
         let args = CgroupArgs {
             sid: resource_args.sid.clone(),
             config: resource_args.config,
@@ -247,8 +257,8 @@ impl Persist for ResourceManagerInner {
             hypervisor: resource_args.hypervisor,
             network: None,
             share_fs: None,
-            rootfs_resource: RootFsResource::new(),
-            volume_resource: VolumeResource::new(),
+            rootfs_resource,
+            volume_resource,
             cgroups_resource: CgroupsResource::restore(
                 args,
                 resource_state.cgroup_state.unwrap_or_default(),
@@ -258,3 +268,84 @@ impl Persist for ResourceManagerInner {
         })
     }
 }
+
+// The share fs is set up on the host before the VM starts, with a bind mount and
+// a virtiofsd for the standalone backend, tear it down if a later device fails
+// so that a failed sandbox doesn't leave them behind.
+async fn teardown_share_fs_on_error<T>(
+    share_fs: &mut Option<Arc<dyn ShareFs>>,
+    result: Result<T>,
+) -> Result<T> {
+    if result.is_err() {
+        if let Some(share_fs) = share_fs.take() {
+            if let Err(e) = share_fs.teardown().await {
+                warn!(
+                    sl!(),
+                    "failed to teardown share fs";
+                    "error" => format!("{:?}", e)
+                );
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use anyhow::anyhow;
+
+    use super::*;
+    use crate::share_fs::ShareFsMount;
+
+    #[derive(Default)]
+    struct MockShareFs {
+        torn_down: AtomicBool,
+    }
+
+    #[async_trait]
+    impl ShareFs for MockShareFs {
+        fn get_share_fs_mount(&self) -> Arc<dyn ShareFsMount> {
+            unimplemented!()
+        }
+        async fn setup_device_before_start_vm(&self, _h: &dyn Hypervisor) -> Result<()> {
+            Ok(())
+        }
+        async fn setup_device_after_start_vm(&self, _h: &dyn Hypervisor) -> Result<()> {
+            Ok(())
+        }
+        async fn get_storages(&self) -> Result<Vec<Storage>> {
+            Ok(vec![])
+        }
+        async fn teardown(&self) -> Result<()> {
+            self.torn_down.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_teardown_share_fs_on_network_error() {
+        let mock = Arc::new(MockShareFs::default());
+        let mut share_fs: Option<Arc<dyn ShareFs>> = Some(mock.clone());
+
+        let network: Result<()> = Err(anyhow!("setup network"));
+        assert!(teardown_share_fs_on_error(&mut share_fs, network)
+            .await
+            .is_err());
+        assert!(mock.torn_down.load(Ordering::SeqCst));
+        assert!(share_fs.is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_keep_share_fs_on_network_success() {
+        let mock = Arc::new(MockShareFs::default());
+        let mut share_fs: Option<Arc<dyn ShareFs>> = Some(mock.clone());
+
+        assert!(teardown_share_fs_on_error(&mut share_fs, Ok(()))
+            .await
+            .is_ok());
+        assert!(!mock.torn_down.load(Ordering::SeqCst));
+        assert!(share_fs.is_some());
+    }
+}
@@ -10,7 +10,7 @@ use std::{sync::Arc, vec::Vec};
 
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use kata_types::mount::Mount;
+use kata_types::{config::TomlConfig, mount::Mount};
 use tokio::sync::RwLock;
 
 use crate::share_fs::ShareFs;
@@ -21,6 +21,7 @@ const ROOTFS: &str = "rootfs";
 pub trait Rootfs: Send + Sync {
     async fn get_guest_rootfs_path(&self) -> Result<String>;
     async fn get_rootfs_mount(&self) -> Result<Vec<oci::Mount>>;
+    async fn get_storage(&self) -> Result<Vec<agent::Storage>>;
 }
 
 #[derive(Default)]
@@ -28,20 +29,17 @@ struct RootFsResourceInner {
     rootfs: Vec<Arc<dyn Rootfs>>,
 }
 
+#[derive(Default)]
 pub struct RootFsResource {
     inner: Arc<RwLock<RootFsResourceInner>>,
-}
-
-impl Default for RootFsResource {
-    fn default() -> Self {
-        Self::new()
-    }
+    overlay: bool,
 }
 
 impl RootFsResource {
-    pub fn new() -> Self {
+    pub fn new(toml_config: &TomlConfig) -> Self {
         Self {
-            inner: Arc::new(RwLock::new(RootFsResourceInner::default())),
+            overlay: toml_config.runtime.share_fs_rootfs_overlay,
+            ..Default::default()
         }
     }
 
@@ -49,6 +47,7 @@ impl RootFsResource {
         &self,
         share_fs: &Option<Arc<dyn ShareFs>>,
         cid: &str,
+        root: &oci::Root,
         bundle_path: &str,
         rootfs_mounts: &[Mount],
     ) -> Result<Arc<dyn Rootfs>> {
@@ -60,13 +59,27 @@ impl RootFsResource {
                 let rootfs = if let Some(share_fs) = share_fs {
                     // share fs rootfs
                     let share_fs_mount = share_fs.get_share_fs_mount();
-                    share_fs_rootfs::ShareFsRootfs::new(&share_fs_mount, cid, bundle_path, layer)
-                        .await
-                        .context("new share fs rootfs")?
+                    share_fs_rootfs::ShareFsRootfs::new(
+                        &share_fs_mount,
+                        cid,
+                        root,
+                        bundle_path,
+                        layer,
+                        self.overlay && !root.readonly,
+                    )
+                    .await
+                    .context("new share fs rootfs")?
                 } else {
                     return Err(anyhow!("unsupported rootfs {:?}", &layer));
                 };
 
+                info!(
+                    sl!(),
+                    "new rootfs";
+                    "cid" => cid,
+                    "readonly" => root.readonly,
+                    "overlay" => self.overlay
+                );
                 let mut inner = self.inner.write().await;
                 let r = Arc::new(rootfs);
                 inner.rootfs.push(r.clone());
@@ -84,11 +97,12 @@ impl RootFsResource {
     pub async fn dump(&self) {
         let inner = self.inner.read().await;
         for r in &inner.rootfs {
+            let guest_path = r.get_guest_rootfs_path().await;
             info!(
                 sl!(),
-                "rootfs {:?}: count {}",
-                r.get_guest_rootfs_path().await,
-                Arc::strong_count(r)
+                "rootfs";
+                "guest_path" => ?guest_path,
+                "count" => Arc::strong_count(r)
             );
         }
     }
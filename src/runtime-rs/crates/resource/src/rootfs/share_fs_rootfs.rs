@@ -4,7 +4,7 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
@@ -12,18 +12,28 @@ use kata_sys_util::mount::Mounter;
 use kata_types::mount::Mount;
 
 use super::{Rootfs, ROOTFS};
-use crate::share_fs::{ShareFsMount, ShareFsRootfsConfig};
+use crate::share_fs::{validate_path_component, ShareFsMount, ShareFsRootfsConfig};
+
+/// Guest directory of the writable overlays on top of the shared rootfs, it's on the
+/// guest tmpfs.
+const KATA_GUEST_ROOTFS_OVERLAY_DIR: &str = "/run/kata-containers/overlay/";
+
+const DRIVER_LOCAL_TYPE: &str = "local";
+const DRIVER_OVERLAYFS_TYPE: &str = "overlayfs";
 
 pub(crate) struct ShareFsRootfs {
     guest_path: String,
+    storages: Vec<agent::Storage>,
 }
 
 impl ShareFsRootfs {
     pub async fn new(
         share_fs_mount: &Arc<dyn ShareFsMount>,
         cid: &str,
+        root: &oci::Root,
         bundle_path: &str,
         rootfs: &Mount,
+        overlay: bool,
     ) -> Result<Self> {
         let bundle_rootfs = format!("{}/{}", bundle_path, ROOTFS);
         rootfs.mount(&bundle_rootfs).context(format!(
@@ -31,22 +41,80 @@ impl ShareFsRootfs {
             &rootfs, &bundle_rootfs
         ))?;
 
-        let mount_result = share_fs_mount
-            .share_rootfs(ShareFsRootfsConfig {
-                cid: cid.to_string(),
-                source: bundle_rootfs.to_string(),
-                target: ROOTFS.to_string(),
-                readonly: false,
-            })
-            .await
-            .context("share rootfs")?;
-
+        let (guest_path, storages) =
+            share_rootfs(share_fs_mount.as_ref(), cid, root, &bundle_rootfs, overlay).await?;
         Ok(ShareFsRootfs {
-            guest_path: mount_result.guest_path,
+            guest_path,
+            storages,
         })
     }
 }
 
+// Share the bundle rootfs mounted at `bundle_rootfs`, with a writable overlay on top
+// of it if `overlay` is set. Returns the guest path of the rootfs along with the
+// storages the agent has to mount.
+async fn share_rootfs(
+    share_fs_mount: &dyn ShareFsMount,
+    cid: &str,
+    root: &oci::Root,
+    bundle_rootfs: &str,
+    overlay: bool,
+) -> Result<(String, Vec<agent::Storage>)> {
+    let mount_result = share_fs_mount
+        .share_rootfs(ShareFsRootfsConfig {
+            cid: cid.to_string(),
+            source: bundle_rootfs.to_string(),
+            target: ROOTFS.to_string(),
+            // the shared rootfs is only the lower directory of the overlay
+            readonly: root.readonly || overlay,
+        })
+        .await
+        .context("share rootfs")?;
+
+    if overlay {
+        return overlay_storages(cid, &mount_result.guest_path).context("rootfs overlay storages");
+    }
+    Ok((mount_result.guest_path, vec![]))
+}
+
+// Storages of a writable overlay on top of the shared rootfs `lower`, the agent creates
+// the upper and work directories before mounting the overlay. Returns the guest path of
+// the overlay along with the storages.
+fn overlay_storages(cid: &str, lower: &str) -> Result<(String, Vec<agent::Storage>)> {
+    validate_path_component(cid)?;
+    let overlay_dir = Path::new(KATA_GUEST_ROOTFS_OVERLAY_DIR).join(cid);
+    let path = |name: &str| overlay_dir.join(name).to_string_lossy().to_string();
+    let (upper, work, merged) = (path("upper"), path("work"), path(ROOTFS));
+
+    let mut storages: Vec<agent::Storage> = [&upper, &work]
+        .iter()
+        .map(|dir| agent::Storage {
+            driver: String::from(DRIVER_LOCAL_TYPE),
+            driver_options: Vec::new(),
+            source: String::from(DRIVER_LOCAL_TYPE),
+            fs_type: String::from(DRIVER_LOCAL_TYPE),
+            fs_group: None,
+            options: Vec::new(),
+            mount_point: dir.to_string(),
+        })
+        .collect();
+    storages.push(agent::Storage {
+        driver: String::from(DRIVER_OVERLAYFS_TYPE),
+        driver_options: Vec::new(),
+        source: String::from("overlay"),
+        fs_type: String::from("overlay"),
+        fs_group: None,
+        options: vec![
+            format!("lowerdir={}", lower),
+            format!("upperdir={}", upper),
+            format!("workdir={}", work),
+        ],
+        mount_point: merged.clone(),
+    });
+
+    Ok((merged, storages))
+}
+
 #[async_trait]
 impl Rootfs for ShareFsRootfs {
     async fn get_guest_rootfs_path(&self) -> Result<String> {
@@ -56,4 +124,103 @@ impl Rootfs for ShareFsRootfs {
     async fn get_rootfs_mount(&self) -> Result<Vec<oci::Mount>> {
         todo!()
     }
+
+    async fn get_storage(&self) -> Result<Vec<agent::Storage>> {
+        Ok(self.storages.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use anyhow::anyhow;
+
+    use super::*;
+    use crate::share_fs::{ShareFsMountResult, ShareFsVolumeConfig};
+
+    // MockShareFsMount records whether the rootfs is shared read only.
+    #[derive(Default)]
+    struct MockShareFsMount {
+        readonly: Mutex<Option<bool>>,
+    }
+
+    #[async_trait]
+    impl ShareFsMount for MockShareFsMount {
+        async fn share_rootfs(&self, config: ShareFsRootfsConfig) -> Result<ShareFsMountResult> {
+            *self.readonly.lock().unwrap() = Some(config.readonly);
+            Ok(ShareFsMountResult {
+                guest_path: format!(
+                    "/run/kata-containers/shared/containers/{}/{}",
+                    config.cid, config.target
+                ),
+                storages: vec![],
+            })
+        }
+
+        async fn share_volume(&self, _config: ShareFsVolumeConfig) -> Result<ShareFsMountResult> {
+            Err(anyhow!("share volume is not supported"))
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_share_rootfs_readonly() {
+        let lower = "/run/kata-containers/shared/containers/cid/rootfs";
+        let overlay_path = "/run/kata-containers/overlay/cid/rootfs";
+        // (root.readonly, overlay, shared readonly, guest path, storages)
+        let cases = [
+            (false, false, false, lower, 0),
+            (true, false, true, lower, 0),
+            (false, true, true, overlay_path, 3),
+            (true, true, true, overlay_path, 3),
+        ];
+        for (root_readonly, overlay, readonly, path, count) in cases {
+            let mock = MockShareFsMount::default();
+            let root = oci::Root {
+                path: "rootfs".to_string(),
+                readonly: root_readonly,
+            };
+            let (guest_path, storages) =
+                share_rootfs(&mock, "cid", &root, "/bundle/rootfs", overlay)
+                    .await
+                    .unwrap();
+            assert_eq!(*mock.readonly.lock().unwrap(), Some(readonly));
+            assert_eq!(guest_path, path);
+            assert_eq!(storages.len(), count);
+        }
+    }
+
+    #[test]
+    fn test_overlay_storages() {
+        let lower = "/run/kata-containers/shared/containers/cid/rootfs";
+        let (guest_path, storages) = overlay_storages("cid", lower).unwrap();
+        assert_eq!(guest_path, "/run/kata-containers/overlay/cid/rootfs");
+        assert_eq!(storages.len(), 3);
+
+        assert_eq!(storages[0].driver, "local");
+        assert_eq!(
+            storages[0].mount_point,
+            "/run/kata-containers/overlay/cid/upper"
+        );
+        assert_eq!(storages[1].driver, "local");
+        assert_eq!(
+            storages[1].mount_point,
+            "/run/kata-containers/overlay/cid/work"
+        );
+
+        let overlay = &storages[2];
+        assert_eq!(overlay.driver, "overlayfs");
+        assert_eq!(overlay.fs_type, "overlay");
+        assert_eq!(overlay.mount_point, guest_path);
+        assert_eq!(
+            overlay.options,
+            vec![
+                format!("lowerdir={}", lower),
+                "upperdir=/run/kata-containers/overlay/cid/upper".to_string(),
+                "workdir=/run/kata-containers/overlay/cid/work".to_string(),
+            ]
+        );
+
+        assert!(overlay_storages("../cid", lower).is_err());
+    }
 }
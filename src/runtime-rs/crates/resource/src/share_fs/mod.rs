@@ -5,11 +5,13 @@
 //
 
 mod share_virtio_fs;
+pub(crate) use share_virtio_fs::KATA_VIRTIO_FS_DEV_TYPE;
 mod share_virtio_fs_inline;
 use share_virtio_fs_inline::ShareVirtioFsInline;
 mod share_virtio_fs_standalone;
 use share_virtio_fs_standalone::ShareVirtioFsStandalone;
 mod utils;
+pub(crate) use utils::validate_path_component;
 mod virtio_fs_share_mount;
 use virtio_fs_share_mount::VirtiofsShareMount;
 
@@ -39,6 +41,17 @@ pub trait ShareFs: Send + Sync {
     async fn setup_device_before_start_vm(&self, h: &dyn Hypervisor) -> Result<()>;
     async fn setup_device_after_start_vm(&self, h: &dyn Hypervisor) -> Result<()>;
     async fn get_storages(&self) -> Result<Vec<Storage>>;
+    /// Undo what setup_device_before_start_vm left on the host.
+    async fn teardown(&self) -> Result<()>;
+}
+
+/// Whether the hypervisor can share host directories with the guest, volumes have
+/// to be provided some other way if it can't.
+pub(crate) async fn is_fs_sharing_supported(h: &dyn Hypervisor) -> Result<bool> {
+    Ok(h.capabilities()
+        .await
+        .context("get hypervisor capabilities")?
+        .is_fs_sharing_supported())
 }
 
 pub struct ShareFsRootfsConfig {
@@ -82,3 +95,89 @@ pub fn new(id: &str, config: &SharedFsInfo) -> Result<Arc<dyn ShareFs>> {
         _ => Err(anyhow!("unsupported shred fs {:?}", &shared_fs)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hypervisor::{device::Device, hypervisor_persist::HypervisorState, VcpuThreadIds};
+    use kata_types::capabilities::{Capabilities, CapabilityBits};
+    use kata_types::config::hypervisor::Hypervisor as HypervisorConfig;
+
+    // StubHypervisor only reports the given capabilities, everything else fails
+    struct StubHypervisor {
+        fs_sharing: bool,
+    }
+
+    #[async_trait]
+    impl Hypervisor for StubHypervisor {
+        async fn prepare_vm(&self, _id: &str, _netns: Option<String>) -> Result<()> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn start_vm(&self, _timeout: i32) -> Result<()> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn stop_vm(&self) -> Result<()> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn pause_vm(&self) -> Result<()> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn save_vm(&self) -> Result<()> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn resume_vm(&self) -> Result<()> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn add_device(&self, _device: Device) -> Result<()> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn remove_device(&self, _device: Device) -> Result<()> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn get_agent_socket(&self) -> Result<String> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn disconnect(&self) {}
+        async fn hypervisor_config(&self) -> HypervisorConfig {
+            HypervisorConfig::default()
+        }
+        async fn get_thread_ids(&self) -> Result<VcpuThreadIds> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn get_pids(&self) -> Result<Vec<u32>> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn cleanup(&self) -> Result<()> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn check(&self) -> Result<()> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn get_jailer_root(&self) -> Result<String> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn save_state(&self) -> Result<HypervisorState> {
+            Err(anyhow!("not supported by the stub"))
+        }
+        async fn capabilities(&self) -> Result<Capabilities> {
+            let mut caps = Capabilities::new();
+            if self.fs_sharing {
+                caps.set(CapabilityBits::FsSharingSupport);
+            }
+            Ok(caps)
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_is_fs_sharing_supported() {
+        let h = StubHypervisor { fs_sharing: false };
+        assert!(!is_fs_sharing_supported(&h).await.unwrap());
+
+        let h = StubHypervisor { fs_sharing: true };
+        assert!(is_fs_sharing_supported(&h).await.unwrap());
+
+        // dragonball shares host directories with (inline) virtio-fs
+        let h = hypervisor::dragonball::Dragonball::new();
+        assert!(is_fs_sharing_supported(&h).await.unwrap());
+    }
+}
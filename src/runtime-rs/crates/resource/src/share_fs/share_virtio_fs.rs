@@ -29,6 +29,7 @@ pub(crate) async fn prepare_virtiofs(
     fs_type: &str,
     id: &str,
     root: &str,
+    thread_pool_size: u32,
 ) -> Result<()> {
     let host_ro_dest = utils::get_host_ro_shared_path(id);
     utils::ensure_dir_exist(&host_ro_dest)?;
@@ -46,7 +47,16 @@ pub(crate) async fn prepare_virtiofs(
         fs_type: fs_type.to_string(),
         queue_size: 0,
         queue_num: 0,
+        thread_pool_size,
     });
     h.add_device(share_fs_device).await.context("add device")?;
     Ok(())
 }
+
+// teardown_virtiofs drops the read only bind mount of prepare_virtiofs, the device
+// itself goes away with the VM.
+pub(crate) fn teardown_virtiofs(id: &str) -> Result<()> {
+    let host_ro_dest = utils::get_host_ro_shared_path(id);
+    mount::umount_all(&host_ro_dest, true).context("umount shared_fs directory")?;
+    Ok(())
+}
@@ -8,14 +8,18 @@ use agent::Storage;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use hypervisor::{
-    device::{Device as HypervisorDevice, ShareFsMountConfig, ShareFsMountType, ShareFsOperation},
+    device::{
+        Device as HypervisorDevice, ShareFsMountConfig, ShareFsMountType, ShareFsOperation,
+        ShareFsRateLimit,
+    },
     Hypervisor,
 };
 use kata_types::config::hypervisor::SharedFsInfo;
 
 use super::{
     share_virtio_fs::{
-        prepare_virtiofs, FS_TYPE_VIRTIO_FS, KATA_VIRTIO_FS_DEV_TYPE, MOUNT_GUEST_TAG,
+        prepare_virtiofs, teardown_virtiofs, FS_TYPE_VIRTIO_FS, KATA_VIRTIO_FS_DEV_TYPE,
+        MOUNT_GUEST_TAG,
     },
     utils, ShareFs, PASSTHROUGH_FS_DIR, *,
 };
@@ -27,6 +31,10 @@ lazy_static! {
 #[derive(Debug, Clone)]
 pub struct ShareVirtioFsInlineConfig {
     pub id: String,
+    // thread_pool_size of the inline virtio-fs backend, 0 means the VMM default
+    pub thread_pool_size: u32,
+    // IO limits of the inline virtio-fs device
+    pub rate_limit: ShareFsRateLimit,
 }
 
 pub struct ShareVirtioFsInline {
@@ -35,9 +43,16 @@ pub struct ShareVirtioFsInline {
 }
 
 impl ShareVirtioFsInline {
-    pub(crate) fn new(id: &str, _config: &SharedFsInfo) -> Result<Self> {
+    pub(crate) fn new(id: &str, config: &SharedFsInfo) -> Result<Self> {
         Ok(Self {
-            config: ShareVirtioFsInlineConfig { id: id.to_string() },
+            config: ShareVirtioFsInlineConfig {
+                id: id.to_string(),
+                thread_pool_size: config.virtio_fs_thread_pool_size,
+                rate_limit: ShareFsRateLimit {
+                    bandwidth: config.virtio_fs_rate_limiter_max_rate,
+                    ops: config.virtio_fs_rate_limiter_max_ops,
+                },
+            },
             share_fs_mount: Arc::new(VirtiofsShareMount::new(id)),
         })
     }
@@ -50,14 +65,20 @@ impl ShareFs for ShareVirtioFsInline {
     }
 
     async fn setup_device_before_start_vm(&self, h: &dyn Hypervisor) -> Result<()> {
-        prepare_virtiofs(h, INLINE_VIRTIO_FS, &self.config.id, "")
-            .await
-            .context("prepare virtiofs")?;
+        prepare_virtiofs(
+            h,
+            INLINE_VIRTIO_FS,
+            &self.config.id,
+            "",
+            self.config.thread_pool_size,
+        )
+        .await
+        .context("prepare virtiofs")?;
         Ok(())
     }
 
     async fn setup_device_after_start_vm(&self, h: &dyn Hypervisor) -> Result<()> {
-        setup_inline_virtiofs(&self.config.id, h)
+        setup_inline_virtiofs(&self.config.id, self.config.rate_limit, h)
             .await
             .context("setup inline virtiofs")?;
         Ok(())
@@ -79,9 +100,17 @@ impl ShareFs for ShareVirtioFsInline {
         storages.push(shared_volume);
         Ok(storages)
     }
+
+    async fn teardown(&self) -> Result<()> {
+        teardown_virtiofs(&self.config.id).context("teardown virtiofs")
+    }
 }
 
-async fn setup_inline_virtiofs(id: &str, h: &dyn Hypervisor) -> Result<()> {
+async fn setup_inline_virtiofs(
+    id: &str,
+    rate_limit: ShareFsRateLimit,
+    h: &dyn Hypervisor,
+) -> Result<()> {
     // - source is the absolute path of PASSTHROUGH_FS_DIR on host, e.g.
     //   /run/kata-containers/shared/sandboxes/<sid>/passthrough
     // - mount point is the path relative to KATA_GUEST_SHARE_DIR in guest
@@ -93,16 +122,69 @@ async fn setup_inline_virtiofs(id: &str, h: &dyn Hypervisor) -> Result<()> {
     let ro_source = utils::get_host_ro_shared_path(id).join(PASSTHROUGH_FS_DIR);
     let source = String::from(ro_source.to_str().unwrap());
 
-    let virtio_fs = HypervisorDevice::ShareFsMount(ShareFsMountConfig {
-        source: source.clone(),
+    let virtio_fs =
+        HypervisorDevice::ShareFsMount(passthrough_mount_config(source.clone(), mnt, rate_limit));
+    h.add_device(virtio_fs)
+        .await
+        .context(format!("fail to attach passthrough fs {:?}", source))
+}
+
+fn passthrough_mount_config(
+    source: String,
+    mount_point: String,
+    rate_limit: ShareFsRateLimit,
+) -> ShareFsMountConfig {
+    ShareFsMountConfig {
+        source,
         fstype: ShareFsMountType::PASSTHROUGH,
-        mount_point: mnt,
+        mount_point,
         config: None,
         tag: String::from(MOUNT_GUEST_TAG),
         op: ShareFsOperation::Mount,
         prefetch_list_path: None,
-    });
-    h.add_device(virtio_fs)
-        .await
-        .context(format!("fail to attach passthrough fs {:?}", source))
+        rate_limit: (!rate_limit.is_unlimited()).then_some(rate_limit),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_thread_pool_size() {
+        let config = SharedFsInfo {
+            virtio_fs_thread_pool_size: 8,
+            ..Default::default()
+        };
+        let share_fs = ShareVirtioFsInline::new("sid", &config).unwrap();
+        assert_eq!(share_fs.config.thread_pool_size, 8);
+
+        let share_fs = ShareVirtioFsInline::new("sid", &SharedFsInfo::default()).unwrap();
+        assert_eq!(share_fs.config.thread_pool_size, 0);
+    }
+
+    #[test]
+    fn test_new_rate_limit() {
+        let config = SharedFsInfo {
+            virtio_fs_rate_limiter_max_rate: 1 << 20,
+            virtio_fs_rate_limiter_max_ops: 1000,
+            ..Default::default()
+        };
+        let share_fs = ShareVirtioFsInline::new("sid", &config).unwrap();
+        let rate_limit = share_fs.config.rate_limit;
+        assert_eq!(rate_limit.bandwidth, 1 << 20);
+        assert_eq!(rate_limit.ops, 1000);
+
+        let mount = passthrough_mount_config("/src".to_string(), "/dst".to_string(), rate_limit);
+        assert_eq!(mount.rate_limit, Some(rate_limit));
+
+        let share_fs = ShareVirtioFsInline::new("sid", &SharedFsInfo::default()).unwrap();
+        assert!(share_fs.config.rate_limit.is_unlimited());
+        let mount = passthrough_mount_config(
+            "/src".to_string(),
+            "/dst".to_string(),
+            share_fs.config.rate_limit,
+        );
+        assert_eq!(mount.rate_limit, None);
+    }
 }
@@ -4,7 +4,7 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use std::{process::Stdio, sync::Arc};
+use std::{path::Path, process::Stdio, sync::Arc};
 
 use agent::Storage;
 use anyhow::{anyhow, Context, Result};
@@ -36,6 +36,8 @@ pub struct ShareVirtioFsStandaloneConfig {
     pub virtio_fs_cache: String,
     // virtio_fs_extra_args passes options to virtiofsd daemon
     pub virtio_fs_extra_args: Vec<String>,
+    // virtio_fs_thread_pool_size is the virtiofsd thread pool size, 0 means the daemon default
+    pub virtio_fs_thread_pool_size: u32,
 }
 
 #[derive(Default)]
@@ -58,24 +60,33 @@ impl ShareVirtioFsStandalone {
                 virtio_fs_daemon: config.virtio_fs_daemon.clone(),
                 virtio_fs_cache: config.virtio_fs_cache.clone(),
                 virtio_fs_extra_args: config.virtio_fs_extra_args.clone(),
+                virtio_fs_thread_pool_size: config.virtio_fs_thread_pool_size,
             },
             share_fs_mount: Arc::new(VirtiofsShareMount::new(id)),
         })
     }
 
-    fn virtiofsd_args(&self, sock_path: &str) -> Result<Vec<String>> {
-        let source_path = get_host_ro_shared_path(&self.config.id);
-        ensure_dir_exist(&source_path)?;
+    fn virtiofsd_args(&self, sock_path: &str, source_path: &Path) -> Result<Vec<String>> {
+        let source_path = source_path
+            .to_str()
+            .ok_or_else(|| anyhow!("invalid source path {:?}", source_path))?;
 
         let mut args: Vec<String> = vec![
             String::from("-f"),
             format!("--socket-path={}", sock_path),
             String::from("-o"),
-            format!("source={}", source_path.to_str().unwrap()),
+            format!("source={}", source_path),
             String::from("-o"),
             format!("cache={}", self.config.virtio_fs_cache),
         ];
 
+        if self.config.virtio_fs_thread_pool_size > 0 {
+            args.push(format!(
+                "--thread-pool-size={}",
+                self.config.virtio_fs_thread_pool_size
+            ));
+        }
+
         if !self.config.virtio_fs_extra_args.is_empty() {
             let mut extra_args: Vec<String> = self.config.virtio_fs_extra_args.clone();
             args.append(&mut extra_args);
@@ -86,7 +97,11 @@ impl ShareVirtioFsStandalone {
 
     async fn setup_virtiofsd(&self) -> Result<()> {
         let sock_path = generate_sock_path(&self.config.jail_root);
-        let args = self.virtiofsd_args(&sock_path).context("virtiofsd args")?;
+        let source_path = get_host_ro_shared_path(&self.config.id);
+        ensure_dir_exist(&source_path)?;
+        let args = self
+            .virtiofsd_args(&sock_path, &source_path)
+            .context("virtiofsd args")?;
 
         let mut cmd = Command::new(&self.config.virtio_fs_daemon);
         let child_cmd = cmd.args(&args).stderr(Stdio::piped());
@@ -172,4 +187,36 @@ impl ShareFs for ShareVirtioFsStandalone {
     async fn get_storages(&self) -> Result<Vec<Storage>> {
         Ok(vec![])
     }
+
+    async fn teardown(&self) -> Result<()> {
+        self.shutdown_virtiofsd()
+            .await
+            .context("shutdown virtiofsd")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtiofsd_args_thread_pool_size() {
+        let mut config = SharedFsInfo {
+            virtio_fs_cache: "auto".to_string(),
+            ..Default::default()
+        };
+        let share_fs = ShareVirtioFsStandalone::new("sid", &config).unwrap();
+        let args = share_fs
+            .virtiofsd_args("/tmp/vhost.sock", Path::new("/tmp/ro"))
+            .unwrap();
+        assert!(!args.iter().any(|a| a.starts_with("--thread-pool-size")));
+
+        config.virtio_fs_thread_pool_size = 16;
+        let share_fs = ShareVirtioFsStandalone::new("sid", &config).unwrap();
+        let args = share_fs
+            .virtiofsd_args("/tmp/vhost.sock", Path::new("/tmp/ro"))
+            .unwrap();
+        assert!(args.contains(&"source=/tmp/ro".to_string()));
+        assert!(args.contains(&"--thread-pool-size=16".to_string()));
+    }
 }
@@ -28,18 +28,27 @@ pub(crate) fn share_to_guest(
     readonly: bool,
     is_volume: bool,
 ) -> Result<String> {
-    let host_dest = do_get_host_path(target, sid, cid, is_volume, false);
+    let host_dest = do_get_host_path(target, sid, cid, is_volume, false)?;
     mount::bind_mount_unchecked(source, &host_dest, readonly)
         .with_context(|| format!("failed to bind mount {} to {}", source, &host_dest))?;
 
     // bind mount remount event is not propagated to mount subtrees, so we have
     // to remount the read only dir mount point directly.
     if readonly {
-        let dst = do_get_host_path(target, sid, cid, is_volume, true);
+        let dst = do_get_host_path(target, sid, cid, is_volume, true)?;
         mount::bind_remount_read_only(&dst).context("bind remount readonly")?;
     }
 
-    Ok(do_get_guest_path(target, cid, is_volume))
+    do_get_guest_path(target, cid, is_volume)
+}
+
+// The ids and targets come from the container spec, make sure they are single path
+// components so that joining them can't escape the shared directory.
+pub(crate) fn validate_path_component(name: &str) -> Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') || name.contains('\0') {
+        return Err(anyhow!("invalid path component {:?}", name));
+    }
+    Ok(())
 }
 // Shared path handling:
 // 1. create two directories for each sandbox:
@@ -57,7 +66,13 @@ pub(crate) fn get_host_rw_shared_path(sid: &str) -> PathBuf {
     Path::new(KATA_HOST_SHARED_DIR).join(sid).join("rw")
 }
 
-fn do_get_guest_any_path(target: &str, cid: &str, is_volume: bool, is_virtiofs: bool) -> String {
+fn do_get_guest_any_path(
+    target: &str,
+    cid: &str,
+    is_volume: bool,
+    is_virtiofs: bool,
+) -> Result<String> {
+    validate_path_component(target)?;
     let dir = PASSTHROUGH_FS_DIR;
     let guest_share_dir = if is_virtiofs {
         Path::new("/").to_path_buf()
@@ -68,12 +83,13 @@ fn do_get_guest_any_path(target: &str, cid: &str, is_volume: bool, is_virtiofs:
     let path = if is_volume && !is_virtiofs {
         guest_share_dir.join(dir).join(target)
     } else {
+        validate_path_component(cid)?;
         guest_share_dir.join(dir).join(cid).join(target)
     };
-    path.to_str().unwrap().to_string()
+    Ok(path.to_str().unwrap().to_string())
 }
 
-pub(crate) fn do_get_guest_path(target: &str, cid: &str, is_volume: bool) -> String {
+pub(crate) fn do_get_guest_path(target: &str, cid: &str, is_volume: bool) -> Result<String> {
     do_get_guest_any_path(target, cid, is_volume, false)
 }
 
@@ -83,7 +99,9 @@ pub(crate) fn do_get_host_path(
     cid: &str,
     is_volume: bool,
     read_only: bool,
-) -> String {
+) -> Result<String> {
+    validate_path_component(target)?;
+    validate_path_component(sid)?;
     let dir = PASSTHROUGH_FS_DIR;
 
     let get_host_path = if read_only {
@@ -95,7 +113,48 @@ pub(crate) fn do_get_host_path(
     let path = if is_volume {
         get_host_path(sid).join(dir).join(target)
     } else {
+        validate_path_component(cid)?;
         get_host_path(sid).join(dir).join(cid).join(target)
     };
-    path.to_str().unwrap().to_string()
+    Ok(path.to_str().unwrap().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADVERSARIAL_NAMES: [&str; 6] = ["", ".", "..", "../etc", "a/../../b", "/etc"];
+
+    #[test]
+    fn test_validate_path_component() {
+        validate_path_component("cid-1234-config").unwrap();
+        validate_path_component("..hidden").unwrap();
+        for name in ADVERSARIAL_NAMES {
+            assert!(validate_path_component(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_do_get_path() {
+        assert_eq!(
+            do_get_host_path("rootfs", "sid", "cid", false, false).unwrap(),
+            "/run/kata-containers/shared/sandboxes/sid/rw/passthrough/cid/rootfs"
+        );
+        assert_eq!(
+            do_get_host_path("vol", "sid", "cid", true, true).unwrap(),
+            "/run/kata-containers/shared/sandboxes/sid/ro/passthrough/vol"
+        );
+        assert_eq!(
+            do_get_guest_path("rootfs", "cid", false).unwrap(),
+            "/run/kata-containers/shared/containers/passthrough/cid/rootfs"
+        );
+
+        for name in ADVERSARIAL_NAMES {
+            assert!(do_get_host_path(name, "sid", "cid", true, false).is_err());
+            assert!(do_get_host_path("vol", name, "cid", true, false).is_err());
+            assert!(do_get_host_path("rootfs", "sid", name, false, false).is_err());
+            assert!(do_get_guest_path(name, "cid", true).is_err());
+            assert!(do_get_guest_path("rootfs", name, false).is_err());
+        }
+    }
 }
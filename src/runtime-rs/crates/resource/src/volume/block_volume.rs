@@ -6,7 +6,7 @@
 
 use anyhow::Result;
 
-use super::Volume;
+use super::{Volume, VolumeKind};
 
 pub(crate) struct BlockVolume {}
 
@@ -29,6 +29,10 @@ impl Volume for BlockVolume {
     fn cleanup(&self) -> Result<()> {
         todo!()
     }
+
+    fn kind(&self) -> VolumeKind {
+        VolumeKind::Block
+    }
 }
 
 pub(crate) fn is_block_volume(_m: &oci::Mount) -> bool {
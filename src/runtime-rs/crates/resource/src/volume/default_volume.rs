@@ -6,7 +6,7 @@
 
 use anyhow::Result;
 
-use super::Volume;
+use super::{Volume, VolumeKind};
 
 pub(crate) struct DefaultVolume {
     mount: oci::Mount,
@@ -33,4 +33,12 @@ impl Volume for DefaultVolume {
     fn cleanup(&self) -> Result<()> {
         todo!()
     }
+
+    fn kind(&self) -> VolumeKind {
+        VolumeKind::Default
+    }
+
+    fn needs_agent_mount(&self) -> bool {
+        false
+    }
 }
@@ -6,35 +6,121 @@
 
 mod block_volume;
 mod default_volume;
+mod mount_options;
 mod share_fs_volume;
 mod shm_volume;
 
-use std::{sync::Arc, vec::Vec};
+use std::{path::Path, sync::Arc, vec::Vec};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use kata_types::config::{default::DEFAULT_MAX_SHM_SIZE_MB, TomlConfig};
 use tokio::sync::RwLock;
 
 use crate::share_fs::ShareFs;
+pub use mount_options::{MountOptions, MountPropagation};
+
+/// VolumeKind: how a volume is provided to the guest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VolumeKind {
+    Block,
+    ShareFs,
+    Shm,
+    Default,
+}
+
+/// VolumeInfo: summary of a volume mount, for debugging.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VolumeInfo {
+    pub kind: VolumeKind,
+    pub destination: String,
+    /// Id of the device backing the volume, if any.
+    pub device_id: Option<String>,
+    pub readonly: bool,
+}
 
 pub trait Volume: Send + Sync {
     fn get_volume_mount(&self) -> Result<Vec<oci::Mount>>;
     fn get_storage(&self) -> Result<Vec<agent::Storage>>;
     fn cleanup(&self) -> Result<()>;
+    fn kind(&self) -> VolumeKind;
+
+    /// Id of the device backing the volume, only block volumes have one.
+    fn device_id(&self) -> Option<String> {
+        None
+    }
+
+    /// Whether the agent has to mount storages for the volume before the container
+    /// is created, otherwise `get_storage` returns nothing worth sending.
+    fn needs_agent_mount(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Default)]
 pub struct VolumeResourceInner {
     volumes: Vec<Arc<dyn Volume>>,
+    // size of the sandbox shm, set by the first container asking for one
+    shm_size: Option<u64>,
 }
 
-#[derive(Default)]
+// The kernel filesystems are set up by the agent inside the guest, they are
+// passed through as is rather than handled as host volumes.
+const AGENT_MANAGED_MOUNT_TYPES: [&str; 4] = ["proc", "sysfs", "cgroup", "mqueue"];
+
 pub struct VolumeResource {
     inner: Arc<RwLock<VolumeResourceInner>>,
+    skip_destinations: Vec<String>,
+    max_shm_size: u64,
+    share_fs_guest_prefix: String,
+}
+
+impl Default for VolumeResource {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(VolumeResourceInner::default())),
+            skip_destinations: vec![],
+            max_shm_size: (DEFAULT_MAX_SHM_SIZE_MB as u64) << 20,
+            share_fs_guest_prefix: String::new(),
+        }
+    }
 }
 
 impl VolumeResource {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(toml_config: &TomlConfig) -> Self {
+        Self {
+            skip_destinations: toml_config.runtime.skip_volume_destinations.clone(),
+            max_shm_size: (toml_config.runtime.max_shm_size_mb as u64) << 20,
+            share_fs_guest_prefix: toml_config.runtime.share_fs_volume_guest_prefix.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Collect the storages the agent has to mount for the volumes of a container.
+    /// Volumes backed by the same host resource, e.g. a block device mounted at two
+    /// destinations, resolve to the same storage, keep only the first one rather
+    /// than asking the agent to mount it twice. Storages shared across the
+    /// containers of a pod are left to the agent, which skips a mount point it has
+    /// already mounted.
+    pub fn get_storages(volumes: &[Arc<dyn Volume>]) -> Result<Vec<agent::Storage>> {
+        let mut storages: Vec<agent::Storage> = vec![];
+        for v in volumes.iter().filter(|v| v.needs_agent_mount()) {
+            for s in v.get_storage().context("get storage")? {
+                if storages
+                    .iter()
+                    .any(|e| e.mount_point == s.mount_point && e.source == s.source)
+                {
+                    info!(
+                        sl!(),
+                        "skip duplicated storage";
+                        "source" => &s.source,
+                        "mount_point" => &s.mount_point
+                    );
+                    continue;
+                }
+                storages.push(s);
+            }
+        }
+        Ok(storages)
     }
 
     pub async fn handler_volumes(
@@ -44,34 +130,45 @@ impl VolumeResource {
         oci_mounts: &[oci::Mount],
     ) -> Result<Vec<Arc<dyn Volume>>> {
         let mut volumes: Vec<Arc<dyn Volume>> = vec![];
-        for m in oci_mounts {
-            let volume: Arc<dyn Volume> = if shm_volume::is_shim_volume(m) {
-                let shm_size = shm_volume::DEFAULT_SHM_SIZE;
-                Arc::new(
-                    shm_volume::ShmVolume::new(m, shm_size)
-                        .with_context(|| format!("new shm volume {:?}", m))?,
-                )
-            } else if share_fs_volume::is_share_fs_volume(m) {
-                Arc::new(
-                    share_fs_volume::ShareFsVolume::new(share_fs, m, cid)
-                        .await
-                        .with_context(|| format!("new share fs volume {:?}", m))?,
-                )
-            } else if block_volume::is_block_volume(m) {
-                Arc::new(
-                    block_volume::BlockVolume::new(m)
-                        .with_context(|| format!("new block volume {:?}", m))?,
-                )
-            } else if is_skip_volume(m) {
-                info!(sl!(), "skip volume {:?}", m);
+        for m in sort_mounts_by_depth(oci_mounts) {
+            if self.is_skip_volume(m) {
+                info!(
+                    sl!(),
+                    "skip volume";
+                    "cid" => cid,
+                    "destination" => &m.destination,
+                    "mount_type" => &m.r#type
+                );
                 continue;
-            } else {
-                Arc::new(
-                    default_volume::DefaultVolume::new(m)
-                        .with_context(|| format!("new default volume {:?}", m))?,
-                )
+            }
+
+            // `nofail` is not a real mount option, don't pass it on
+            let nofail = MountOptions::parse(&m.options).nofail;
+            let mut m = m.clone();
+            m.options.retain(|o| o != "nofail");
+
+            let volume = match self.new_volume(share_fs, cid, &m).await {
+                Ok(volume) => volume,
+                Err(e) if nofail => {
+                    warn!(
+                        sl!(),
+                        "skip nofail volume";
+                        "cid" => cid,
+                        "destination" => &m.destination,
+                        "error" => format!("{:?}", e)
+                    );
+                    continue;
+                }
+                Err(e) => return Err(e),
             };
 
+            info!(
+                sl!(),
+                "new volume";
+                "cid" => cid,
+                "destination" => &m.destination,
+                "mount_type" => &m.r#type
+            );
             volumes.push(volume.clone());
             let mut inner = self.inner.write().await;
             inner.volumes.push(volume);
@@ -80,20 +177,499 @@ impl VolumeResource {
         Ok(volumes)
     }
 
+    async fn new_volume(
+        &self,
+        share_fs: &Option<Arc<dyn ShareFs>>,
+        cid: &str,
+        m: &oci::Mount,
+    ) -> Result<Arc<dyn Volume>> {
+        let volume: Arc<dyn Volume> = if is_agent_managed_mount(m) {
+            Arc::new(
+                default_volume::DefaultVolume::new(m)
+                    .with_context(|| format!("new default volume {:?}", m))?,
+            )
+        } else if shm_volume::is_shim_volume(m) {
+            let shm_size = shm_volume::get_shm_size(m).context("get shm size")?;
+            if shm_size > self.max_shm_size {
+                return Err(anyhow!(
+                    "shm size {} exceeds the maximum {}",
+                    shm_size,
+                    self.max_shm_size
+                ));
+            }
+            // the shm storage is mounted once for the whole sandbox, the agent
+            // skips it for later containers, so only the first size applies
+            if shm_size > 0 {
+                let mut inner = self.inner.write().await;
+                match inner.shm_size {
+                    Some(size) if size != shm_size => warn!(
+                        sl!(),
+                        "shm size ignored, the sandbox shm is already set up";
+                        "cid" => cid,
+                        "shm_size" => shm_size,
+                        "sandbox_shm_size" => size
+                    ),
+                    Some(_) => {}
+                    None => inner.shm_size = Some(shm_size),
+                }
+            }
+            Arc::new(
+                shm_volume::ShmVolume::new(m, shm_size)
+                    .with_context(|| format!("new shm volume {:?}", m))?,
+            )
+        } else if share_fs_volume::is_share_fs_volume(m) {
+            Arc::new(
+                share_fs_volume::ShareFsVolume::new(share_fs, m, cid, &self.share_fs_guest_prefix)
+                    .await
+                    .with_context(|| format!("new share fs volume {:?}", m))?,
+            )
+        } else if block_volume::is_block_volume(m) {
+            Arc::new(
+                block_volume::BlockVolume::new(m)
+                    .with_context(|| format!("new block volume {:?}", m))?,
+            )
+        } else {
+            Arc::new(
+                default_volume::DefaultVolume::new(m)
+                    .with_context(|| format!("new default volume {:?}", m))?,
+            )
+        };
+        Ok(volume)
+    }
+
     pub async fn dump(&self) {
         let inner = self.inner.read().await;
         for v in &inner.volumes {
+            let destinations: Vec<String> = v
+                .get_volume_mount()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|m| m.destination)
+                .collect();
             info!(
                 sl!(),
-                "volume mount {:?}: count {}",
-                v.get_volume_mount(),
-                Arc::strong_count(v)
+                "volume";
+                "kind" => ?v.kind(),
+                "destinations" => ?destinations,
+                "count" => Arc::strong_count(v)
             );
         }
     }
+
+    /// List the mounts of all volumes along with the kind of their volume.
+    pub async fn list(&self) -> Vec<VolumeInfo> {
+        let inner = self.inner.read().await;
+        let mut infos = vec![];
+        for v in &inner.volumes {
+            for m in v.get_volume_mount().unwrap_or_default() {
+                infos.push(VolumeInfo {
+                    kind: v.kind(),
+                    readonly: MountOptions::parse(&m.options).readonly,
+                    destination: m.destination,
+                    device_id: v.device_id(),
+                });
+            }
+        }
+        infos
+    }
+
+    /// Render what the volumes mount in the guest in fstab format, for debugging. The
+    /// storages mounted by the agent come first, then the container mounts.
+    pub async fn fstab_preview(&self) -> String {
+        let inner = self.inner.read().await;
+        let mut lines = vec![];
+        for v in &inner.volumes {
+            if v.needs_agent_mount() {
+                for s in v.get_storage().unwrap_or_default() {
+                    lines.push(fstab_entry(
+                        &s.source,
+                        &s.mount_point,
+                        &s.fs_type,
+                        &s.options,
+                    ));
+                }
+            }
+            for m in v.get_volume_mount().unwrap_or_default() {
+                lines.push(fstab_entry(
+                    &m.source,
+                    &m.destination,
+                    &m.r#type,
+                    &m.options,
+                ));
+            }
+        }
+        lines.iter().map(|l| format!("{}\n", l)).collect()
+    }
+
+    fn is_skip_volume(&self, m: &oci::Mount) -> bool {
+        self.skip_destinations.contains(&m.destination)
+    }
+}
+
+// Parent mounts must be set up before the nested ones, e.g. /data before /data/sub,
+// otherwise the nested mount is shadowed. The sort is stable, so mounts of the same
+// depth keep their original order.
+fn sort_mounts_by_depth(oci_mounts: &[oci::Mount]) -> Vec<&oci::Mount> {
+    let mut mounts: Vec<&oci::Mount> = oci_mounts.iter().collect();
+    mounts.sort_by_key(|m| Path::new(&m.destination).components().count());
+    mounts
+}
+
+fn fstab_entry(source: &str, target: &str, fs_type: &str, options: &[String]) -> String {
+    let options = if options.is_empty() {
+        String::from("defaults")
+    } else {
+        options.join(",")
+    };
+    format!(
+        "{} {} {} {} 0 0",
+        fstab_escape(source),
+        fstab_escape(target),
+        fstab_escape(fs_type),
+        fstab_escape(&options)
+    )
 }
 
-fn is_skip_volume(_m: &oci::Mount) -> bool {
-    // TODO: support volume check
-    false
+// Escape the field separators as the kernel does in /proc/mounts.
+fn fstab_escape(field: &str) -> String {
+    field
+        .replace('\\', "\\134")
+        .replace(' ', "\\040")
+        .replace('\t', "\\011")
+        .replace('\n', "\\012")
+}
+
+fn is_agent_managed_mount(m: &oci::Mount) -> bool {
+    AGENT_MANAGED_MOUNT_TYPES.contains(&m.r#type.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_mount(destination: &str, r#type: &str, source: &str) -> oci::Mount {
+        oci::Mount {
+            destination: destination.to_string(),
+            r#type: r#type.to_string(),
+            source: source.to_string(),
+            options: vec![],
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_handler_volumes_nested_order() {
+        let mounts = vec![
+            new_mount("/data/sub/dir", "tmpfs", "tmpfs"),
+            new_mount("/data/sub", "tmpfs", "a"),
+            new_mount("/data", "tmpfs", "tmpfs"),
+            new_mount("/other/sub", "tmpfs", "b"),
+            new_mount("/tmp", "tmpfs", "tmpfs"),
+        ];
+
+        let volume_resource = VolumeResource::default();
+        let volumes = volume_resource
+            .handler_volumes(&None, "cid", &mounts)
+            .await
+            .unwrap();
+
+        let destinations: Vec<String> = volumes
+            .iter()
+            .flat_map(|v| v.get_volume_mount().unwrap())
+            .map(|m| m.destination)
+            .collect();
+        assert_eq!(
+            destinations,
+            vec!["/data", "/tmp", "/data/sub", "/other/sub", "/data/sub/dir"]
+        );
+    }
+
+    type Records = Arc<std::sync::Mutex<Vec<(String, Vec<String>)>>>;
+
+    // TestDrain records the message and the keys of each log record.
+    struct TestDrain(Records);
+
+    struct KeyCollector(Vec<String>);
+
+    impl slog::Serializer for KeyCollector {
+        fn emit_arguments(&mut self, key: slog::Key, _val: &std::fmt::Arguments) -> slog::Result {
+            self.0.push(key.to_string());
+            Ok(())
+        }
+    }
+
+    impl slog::Drain for TestDrain {
+        type Ok = ();
+        type Err = slog::Never;
+
+        fn log(
+            &self,
+            record: &slog::Record,
+            values: &slog::OwnedKVList,
+        ) -> std::result::Result<(), slog::Never> {
+            let mut keys = KeyCollector(vec![]);
+            slog::KV::serialize(&record.kv(), record, &mut keys).unwrap();
+            slog::KV::serialize(values, record, &mut keys).unwrap();
+            self.0
+                .lock()
+                .unwrap()
+                .push((record.msg().to_string(), keys.0));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_handler_volumes_log_fields() {
+        let records = Records::default();
+        let logger = slog::Logger::root(TestDrain(records.clone()), slog::o!());
+
+        let mut toml_config = TomlConfig::default();
+        toml_config.runtime.skip_volume_destinations = vec!["/skip".to_string()];
+        let volume_resource = VolumeResource::new(&toml_config);
+        let mounts = vec![
+            new_mount("/tmp", "tmpfs", "tmpfs"),
+            new_mount("/skip", "tmpfs", "tmpfs"),
+        ];
+        slog_scope::scope(&logger, || {
+            futures::executor::block_on(volume_resource.handler_volumes(&None, "cid", &mounts))
+        })
+        .unwrap();
+
+        let records = records.lock().unwrap();
+        for msg in ["new volume", "skip volume"] {
+            let (_, keys) = records.iter().find(|(m, _)| m == msg).unwrap();
+            for key in ["cid", "destination", "mount_type", "subsystem"] {
+                assert!(keys.iter().any(|k| k == key), "{} misses {}", msg, key);
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_handler_volumes_max_shm_size() {
+        let mut toml_config = TomlConfig::default();
+        toml_config.runtime.max_shm_size_mb = 64;
+        let volume_resource = VolumeResource::new(&toml_config);
+
+        for (size, ok) in [("size=63m", true), ("size=64m", true), ("size=65m", false)] {
+            let mut m = new_mount("/dev/shm", "tmpfs", "shm");
+            m.options = vec![size.to_string()];
+            let result = volume_resource.handler_volumes(&None, "cid", &[m]).await;
+            assert_eq!(result.is_ok(), ok, "{}", size);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_needs_agent_mount() {
+        let m = new_mount("/data", "tmpfs", "tmpfs");
+        let v = default_volume::DefaultVolume::new(&m).unwrap();
+        assert!(!v.needs_agent_mount());
+
+        let m = new_mount("/dev/shm", "tmpfs", "shm");
+        let v = shm_volume::ShmVolume::new(&m, shm_volume::DEFAULT_SHM_SIZE).unwrap();
+        assert!(v.needs_agent_mount());
+        let v = shm_volume::ShmVolume::new(&m, 0).unwrap();
+        assert!(!v.needs_agent_mount());
+
+        // without fs sharing nothing is shared, so there's nothing to mount
+        let m = new_mount("/data", "bind", "/tmp");
+        let v = share_fs_volume::ShareFsVolume::new(&None, &m, "cid", "")
+            .await
+            .unwrap();
+        assert!(!v.needs_agent_mount());
+
+        let v = block_volume::BlockVolume::new(&m).unwrap();
+        assert!(v.needs_agent_mount());
+    }
+
+    #[actix_rt::test]
+    async fn test_volume_kind() {
+        let m = new_mount("/data", "tmpfs", "tmpfs");
+        let v = default_volume::DefaultVolume::new(&m).unwrap();
+        assert_eq!(v.kind(), VolumeKind::Default);
+
+        let m = new_mount("/dev/shm", "tmpfs", "shm");
+        let v = shm_volume::ShmVolume::new(&m, shm_volume::DEFAULT_SHM_SIZE).unwrap();
+        assert_eq!(v.kind(), VolumeKind::Shm);
+
+        let m = new_mount("/data", "bind", "/tmp");
+        let v = share_fs_volume::ShareFsVolume::new(&None, &m, "cid", "")
+            .await
+            .unwrap();
+        assert_eq!(v.kind(), VolumeKind::ShareFs);
+
+        let v = block_volume::BlockVolume::new(&m).unwrap();
+        assert_eq!(v.kind(), VolumeKind::Block);
+    }
+
+    #[actix_rt::test]
+    async fn test_list_volumes() {
+        let mut ro = new_mount("/ro", "tmpfs", "tmpfs");
+        ro.options = vec!["ro".to_string()];
+        let mounts = vec![
+            new_mount("/proc", "proc", "proc"),
+            ro,
+            new_mount("/dev/shm", "tmpfs", "shm"),
+        ];
+
+        let volume_resource = VolumeResource::default();
+        volume_resource
+            .handler_volumes(&None, "cid", &mounts)
+            .await
+            .unwrap();
+
+        let infos = volume_resource.list().await;
+        let summary: Vec<(VolumeKind, &str, bool)> = infos
+            .iter()
+            .map(|i| (i.kind, i.destination.as_str(), i.readonly))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (VolumeKind::Default, "/proc", false),
+                (VolumeKind::Default, "/ro", true),
+                (VolumeKind::Shm, "/dev/shm", false),
+            ]
+        );
+        assert!(infos.iter().all(|i| i.device_id.is_none()));
+    }
+
+    #[test]
+    fn test_is_agent_managed_mount() {
+        for t in ["proc", "sysfs", "cgroup", "mqueue"] {
+            assert!(is_agent_managed_mount(&new_mount("/x", t, t)));
+        }
+        assert!(!is_agent_managed_mount(&new_mount("/x", "bind", "/x")));
+        assert!(!is_agent_managed_mount(&new_mount("/x", "tmpfs", "tmpfs")));
+    }
+
+    #[actix_rt::test]
+    async fn test_handler_volumes_skip() {
+        let mut toml_config = TomlConfig::default();
+        toml_config.runtime.skip_volume_destinations = vec!["/etc/secret".to_string()];
+        let volume_resource = VolumeResource::new(&toml_config);
+
+        let mounts = vec![
+            new_mount("/proc", "proc", "proc"),
+            new_mount("/sys", "sysfs", "sysfs"),
+            new_mount("/dev/mqueue", "mqueue", "mqueue"),
+            new_mount("/sys/fs/cgroup", "cgroup", "cgroup"),
+            new_mount("/etc/secret", "bind", "/no/such/secret"),
+        ];
+        let volumes = volume_resource
+            .handler_volumes(&None, "cid", &mounts)
+            .await
+            .unwrap();
+
+        // the kernel filesystems are passed through to the agent unchanged, the
+        // mount in the skip list is dropped
+        let volume_mounts: Vec<oci::Mount> = volumes
+            .iter()
+            .flat_map(|v| v.get_volume_mount().unwrap())
+            .collect();
+        assert_eq!(volume_mounts, mounts[..4].to_vec());
+        for v in &volumes {
+            assert!(v.get_storage().unwrap().is_empty());
+        }
+    }
+
+    struct StorageVolume(Vec<agent::Storage>);
+
+    impl Volume for StorageVolume {
+        fn get_volume_mount(&self) -> Result<Vec<oci::Mount>> {
+            Ok(vec![])
+        }
+        fn get_storage(&self) -> Result<Vec<agent::Storage>> {
+            Ok(self.0.clone())
+        }
+        fn cleanup(&self) -> Result<()> {
+            Ok(())
+        }
+        fn kind(&self) -> VolumeKind {
+            VolumeKind::Block
+        }
+    }
+
+    fn new_storage(source: &str, mount_point: &str) -> agent::Storage {
+        agent::Storage {
+            driver: "blk".to_string(),
+            source: source.to_string(),
+            mount_point: mount_point.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_storages_dedup() {
+        let shared = new_storage("/dev/vda", "/run/kata-containers/vda");
+        let volumes: Vec<Arc<dyn Volume>> = vec![
+            Arc::new(StorageVolume(vec![shared.clone()])),
+            Arc::new(StorageVolume(vec![
+                shared.clone(),
+                new_storage("/dev/vdb", "/run/kata-containers/vdb"),
+            ])),
+            // same source mounted at another guest path is a different storage
+            Arc::new(StorageVolume(vec![new_storage(
+                "/dev/vda",
+                "/run/kata-containers/vda-2",
+            )])),
+        ];
+
+        let storages = VolumeResource::get_storages(&volumes).unwrap();
+        let mount_points: Vec<&str> = storages.iter().map(|s| s.mount_point.as_str()).collect();
+        assert_eq!(
+            mount_points,
+            vec![
+                "/run/kata-containers/vda",
+                "/run/kata-containers/vdb",
+                "/run/kata-containers/vda-2",
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_handler_volumes_nofail() {
+        // a bind mount of / can't be shared
+        let mut broken = new_mount("/broken", "bind", "/");
+        let volume_resource = VolumeResource::default();
+        assert!(volume_resource
+            .handler_volumes(&None, "cid", &[broken.clone()])
+            .await
+            .is_err());
+
+        broken.options = vec!["ro".to_string(), "nofail".to_string()];
+        let mounts = vec![broken, new_mount("/tmp", "tmpfs", "tmpfs")];
+        let volumes = volume_resource
+            .handler_volumes(&None, "cid", &mounts)
+            .await
+            .unwrap();
+        assert_eq!(volumes.len(), 1);
+        assert_eq!(
+            volumes[0].get_volume_mount().unwrap()[0].destination,
+            "/tmp"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_fstab_preview() {
+        let mut shm = new_mount("/dev/shm", "tmpfs", "shm");
+        shm.options = vec!["size=1m".to_string()];
+        let mounts = vec![new_mount("/my tmp", "tmpfs", "tmpfs"), shm];
+
+        let volume_resource = VolumeResource::default();
+        assert_eq!(volume_resource.fstab_preview().await, "");
+        volume_resource
+            .handler_volumes(&None, "cid", &mounts)
+            .await
+            .unwrap();
+
+        let preview = volume_resource.fstab_preview().await;
+        let lines: Vec<&str> = preview.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "tmpfs /my\\040tmp tmpfs defaults 0 0",
+                "shm /run/kata-containers/sandbox/shm tmpfs noexec,nosuid,nodev,mode=1777,size=1048576 0 0",
+                "/run/kata-containers/sandbox/shm /dev/shm bind rbind 0 0",
+            ]
+        );
+    }
 }
@@ -0,0 +1,175 @@
+// Copyright (c) 2019-2022 Alibaba Cloud
+// Copyright (c) 2019-2022 Ant Group
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::collections::HashMap;
+
+/// MountPropagation: propagation type requested by a mount option.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MountPropagation {
+    Shared,
+    Slave,
+    Private,
+    Unbindable,
+}
+
+/// MountOptions: the options of an OCI mount, with the well known flags parsed out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MountOptions {
+    pub readonly: bool,
+    pub nosuid: bool,
+    pub nodev: bool,
+    pub noexec: bool,
+    pub sync: bool,
+    /// Failing to set up the volume is not fatal to the container.
+    pub nofail: bool,
+    /// Propagation type and whether it applies recursively (`rshared` etc.).
+    pub propagation: Option<(MountPropagation, bool)>,
+    /// `key=value` options, the last one wins for a duplicated key.
+    pub values: HashMap<String, String>,
+    /// Options which are neither a known flag nor a `key=value` pair, in order.
+    pub others: Vec<String>,
+}
+
+impl MountOptions {
+    pub fn parse<S: AsRef<str>>(options: &[S]) -> Self {
+        let mut opts = MountOptions::default();
+        for option in options {
+            let option = option.as_ref();
+            match option {
+                "ro" => opts.readonly = true,
+                "rw" => opts.readonly = false,
+                "nosuid" => opts.nosuid = true,
+                "suid" => opts.nosuid = false,
+                "nodev" => opts.nodev = true,
+                "dev" => opts.nodev = false,
+                "noexec" => opts.noexec = true,
+                "exec" => opts.noexec = false,
+                "sync" => opts.sync = true,
+                "async" => opts.sync = false,
+                "nofail" => opts.nofail = true,
+                "shared" => opts.propagation = Some((MountPropagation::Shared, false)),
+                "rshared" => opts.propagation = Some((MountPropagation::Shared, true)),
+                "slave" => opts.propagation = Some((MountPropagation::Slave, false)),
+                "rslave" => opts.propagation = Some((MountPropagation::Slave, true)),
+                "private" => opts.propagation = Some((MountPropagation::Private, false)),
+                "rprivate" => opts.propagation = Some((MountPropagation::Private, true)),
+                "unbindable" => opts.propagation = Some((MountPropagation::Unbindable, false)),
+                "runbindable" => opts.propagation = Some((MountPropagation::Unbindable, true)),
+                _ => match option.split_once('=') {
+                    Some((key, value)) => {
+                        opts.values.insert(key.to_string(), value.to_string());
+                    }
+                    None => opts.others.push(option.to_string()),
+                },
+            }
+        }
+        opts
+    }
+
+    /// The flags as storage options for the agent. Note the agent mounts bind storages
+    /// with a single bind mount, which ignores these flags, they only take effect on
+    /// storages mounted with a filesystem type of their own.
+    pub fn storage_options(&self) -> Vec<String> {
+        [
+            (self.readonly, "ro"),
+            (self.nosuid, "nosuid"),
+            (self.nodev, "nodev"),
+            (self.noexec, "noexec"),
+            (self.sync, "sync"),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, o)| o.to_string())
+        .collect()
+    }
+
+    /// Value of a `key=value` option.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|v| v.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flags() {
+        let opts = MountOptions::parse::<&str>(&[]);
+        assert_eq!(opts, MountOptions::default());
+
+        let opts =
+            MountOptions::parse(&["ro", "nosuid", "nodev", "noexec", "sync", "nofail", "bind"]);
+        assert!(opts.readonly);
+        assert!(opts.nosuid);
+        assert!(opts.nodev);
+        assert!(opts.noexec);
+        assert!(opts.sync);
+        assert!(opts.nofail);
+        assert_eq!(opts.propagation, None);
+        assert_eq!(opts.others, vec!["bind".to_string()]);
+
+        // the last one wins
+        let opts =
+            MountOptions::parse(&["ro", "nosuid", "sync", "rw", "suid", "dev", "exec", "async"]);
+        assert_eq!(opts, MountOptions::default());
+    }
+
+    #[test]
+    fn test_parse_propagation() {
+        let cases = [
+            ("shared", MountPropagation::Shared, false),
+            ("rshared", MountPropagation::Shared, true),
+            ("slave", MountPropagation::Slave, false),
+            ("rslave", MountPropagation::Slave, true),
+            ("private", MountPropagation::Private, false),
+            ("rprivate", MountPropagation::Private, true),
+            ("unbindable", MountPropagation::Unbindable, false),
+            ("runbindable", MountPropagation::Unbindable, true),
+        ];
+        for (option, propagation, recursive) in cases {
+            let opts = MountOptions::parse(&["rbind", option]);
+            assert_eq!(
+                opts.propagation,
+                Some((propagation, recursive)),
+                "{}",
+                option
+            );
+        }
+
+        let opts = MountOptions::parse(&["rshared", "private"]);
+        assert_eq!(opts.propagation, Some((MountPropagation::Private, false)));
+    }
+
+    #[test]
+    fn test_storage_options() {
+        assert!(MountOptions::default().storage_options().is_empty());
+
+        let opts = MountOptions::parse(&["rbind", "sync", "noexec", "nodev", "nosuid", "ro"]);
+        assert_eq!(
+            opts.storage_options(),
+            vec!["ro", "nosuid", "nodev", "noexec", "sync"]
+        );
+
+        // only the flags are kept
+        let opts = MountOptions::parse(&["bind", "rw", "nodev", "rprivate", "size=1m"]);
+        assert_eq!(opts.storage_options(), vec!["nodev"]);
+    }
+
+    #[test]
+    fn test_parse_values() {
+        let opts = MountOptions::parse(&["size=64m", "mode=1777", "size=128m", "ro"]);
+        assert_eq!(opts.get("size"), Some("128m"));
+        assert_eq!(opts.get("mode"), Some("1777"));
+        assert_eq!(opts.get("uid"), None);
+        assert!(opts.readonly);
+        assert!(opts.others.is_empty());
+
+        // only the first `=` separates the key
+        let opts = MountOptions::parse(&["context=a=b"]);
+        assert_eq!(opts.get("context"), Some("a=b"));
+    }
+}
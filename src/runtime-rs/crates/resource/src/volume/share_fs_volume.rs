@@ -4,14 +4,26 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use std::{path::Path, sync::Arc};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::{anyhow, Context, Result};
 
-use super::Volume;
-use crate::share_fs::{ShareFs, ShareFsVolumeConfig};
+use super::{MountOptions, Volume, VolumeKind};
+use crate::share_fs::{
+    validate_path_component, ShareFs, ShareFsMount, ShareFsVolumeConfig, KATA_VIRTIO_FS_DEV_TYPE,
+};
 use kata_types::mount;
 
+/// Mount option to share the mounts nested under the volume source as well, e.g.
+/// `kata.virtiofs.submounts=true`.
+pub const KATA_VIRTIOFS_SUBMOUNTS_OPTION: &str = "kata.virtiofs.submounts";
+
+const PROC_SELF_MOUNTINFO: &str = "/proc/self/mountinfo";
+
 // copy file to container's rootfs if filesystem sharing is not supported, otherwise
 // bind mount it in the shared directory.
 // Ignore /dev, directories and all other device files. We handle
@@ -28,9 +40,14 @@ impl ShareFsVolume {
         share_fs: &Option<Arc<dyn ShareFs>>,
         m: &oci::Mount,
         cid: &str,
+        guest_prefix: &str,
     ) -> Result<Self> {
-        let file_name = Path::new(&m.source).file_name().unwrap().to_str().unwrap();
-        let file_name = generate_mount_path(cid, file_name);
+        let file_name = Path::new(&m.source)
+            .file_name()
+            .and_then(|f| f.to_str())
+            .ok_or_else(|| anyhow!("invalid mount source {:?}", &m.source))?;
+        let file_name = generate_mount_path(cid, file_name).context("generate mount path")?;
+        let (submounts, mut options) = take_submounts_option(&m.options)?;
 
         let mut volume = Self {
             mounts: vec![],
@@ -50,11 +67,18 @@ impl ShareFsVolume {
 
                 if src.is_file() {
                     // TODO: copy file
-                    debug!(sl!(), "FIXME: copy file {}", &m.source);
+                    debug!(
+                        sl!(),
+                        "FIXME: copy file";
+                        "cid" => cid,
+                        "destination" => &m.destination
+                    );
                 } else {
                     debug!(
                         sl!(),
-                        "Ignoring non-regular file as FS sharing not supported. mount: {:?}", m
+                        "ignoring non-regular file as FS sharing not supported";
+                        "cid" => cid,
+                        "destination" => &m.destination
                     );
                 }
             }
@@ -65,8 +89,8 @@ impl ShareFsVolume {
                         cid: cid.to_string(),
                         source: m.source.clone(),
                         target: file_name,
-                        readonly: m.options.iter().any(|o| *o == "ro"),
-                        mount_options: m.options.clone(),
+                        readonly: MountOptions::parse(&options).readonly,
+                        mount_options: options.clone(),
                         mount: m.clone(),
                     })
                     .await
@@ -74,13 +98,45 @@ impl ShareFsVolume {
 
                 // set storages for the volume
                 volume.storages = mount_result.storages;
+                let mut guest_path = mount_result.guest_path;
+
+                // watchable and ephemeral volumes aren't mounted from the source directly
+                if submounts && volume.storages.is_empty() && Path::new(&m.source).is_dir() {
+                    let source = fs::canonicalize(&m.source)
+                        .with_context(|| format!("canonicalize {:?}", &m.source))?;
+                    let mountinfo = fs::read_to_string(PROC_SELF_MOUNTINFO)
+                        .with_context(|| format!("read {}", PROC_SELF_MOUNTINFO))?;
+                    let submounts = parse_submounts(&mountinfo, &source);
+                    if !submounts.is_empty() {
+                        let mut storages = share_submounts(
+                            share_fs_mount.as_ref(),
+                            cid,
+                            &source,
+                            &submounts,
+                            &guest_path,
+                            &options,
+                        )
+                        .await
+                        .context("share submounts")?;
+                        volume.storages.append(&mut storages);
+                        set_recursive_bind(&mut options);
+                    }
+                }
+
+                // the container still mounts the volume at its original destination
+                if let Some(storage) = guest_prefix_storage(guest_prefix, cid, m, &guest_path)
+                    .context("remap volume under guest prefix")?
+                {
+                    guest_path = storage.mount_point.clone();
+                    volume.storages.push(storage);
+                }
 
                 // set mount for the volume
                 volume.mounts.push(oci::Mount {
                     destination: m.destination.clone(),
                     r#type: "bind".to_string(),
-                    source: mount_result.guest_path,
-                    options: m.options.clone(),
+                    source: guest_path,
+                    options,
                 });
             }
         }
@@ -100,6 +156,16 @@ impl Volume for ShareFsVolume {
     fn cleanup(&self) -> Result<()> {
         todo!()
     }
+
+    fn kind(&self) -> VolumeKind {
+        VolumeKind::ShareFs
+    }
+
+    // Plain shared volumes are bind mounted from the share fs mount point, only
+    // watchable and ephemeral volumes come with storages of their own.
+    fn needs_agent_mount(&self) -> bool {
+        !self.storages.is_empty()
+    }
 }
 
 pub(crate) fn is_share_fs_volume(m: &oci::Mount) -> bool {
@@ -128,16 +194,384 @@ fn is_host_device(dest: &str) -> bool {
     false
 }
 
+// Storage for the agent to bind mount the shared volume at `<prefix>/<cid>/<destination>`
+// in the guest, none if no prefix is configured.
+fn guest_prefix_storage(
+    prefix: &str,
+    cid: &str,
+    m: &oci::Mount,
+    guest_path: &str,
+) -> Result<Option<agent::Storage>> {
+    if prefix.is_empty() {
+        return Ok(None);
+    }
+    validate_path_component(cid)?;
+
+    let destination = Path::new(&m.destination);
+    if destination
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+    {
+        return Err(anyhow!("invalid mount destination {:?}", &m.destination));
+    }
+    let mount_point = Path::new(prefix)
+        .join(cid)
+        .join(destination.strip_prefix("/").unwrap_or(destination));
+
+    Ok(Some(agent::Storage {
+        driver: String::from(KATA_VIRTIO_FS_DEV_TYPE),
+        driver_options: Vec::new(),
+        source: guest_path.to_string(),
+        fs_type: String::from("bind"),
+        fs_group: None,
+        options: bind_storage_options("rbind", &m.options),
+        mount_point: mount_point.to_string_lossy().to_string(),
+    }))
+}
+
+// The bind storages mounted by the agent carry the flags of the volume, an empty
+// option list only gives the bind option. The agent doesn't remount bind storages
+// so the flags aren't enforced there, it's the container's own bind mount of the
+// volume, remounted with the OCI mount options, that applies them.
+fn bind_storage_options(bind: &str, options: &[String]) -> Vec<String> {
+    let mut storage_options = vec![bind.to_string()];
+    storage_options.append(&mut MountOptions::parse(options).storage_options());
+    storage_options
+}
+
+// Remove the submounts option from the mount options, it's not a real mount option.
+fn take_submounts_option(options: &[String]) -> Result<(bool, Vec<String>)> {
+    let submounts = match MountOptions::parse(options).get(KATA_VIRTIOFS_SUBMOUNTS_OPTION) {
+        Some(value) => value.parse().with_context(|| {
+            format!(
+                "parse mount option {}={:?}",
+                KATA_VIRTIOFS_SUBMOUNTS_OPTION, value
+            )
+        })?,
+        None => false,
+    };
+    let rest = options
+        .iter()
+        .filter(|o| !matches!(o.split_once('='), Some((KATA_VIRTIOFS_SUBMOUNTS_OPTION, _))))
+        .cloned()
+        .collect();
+    Ok((submounts, rest))
+}
+
+// Mount points nested under `source`, parents first.
+fn parse_submounts(mountinfo: &str, source: &Path) -> Vec<PathBuf> {
+    let mut submounts: Vec<PathBuf> = mountinfo
+        .lines()
+        // the 5th field is the mount point
+        .filter_map(|line| line.split(' ').nth(4))
+        .map(|p| PathBuf::from(unescape_mount_path(p)))
+        .filter(|p| p != source && p.starts_with(source))
+        .collect();
+    submounts.sort_by_cached_key(|p| (p.components().count(), p.clone()));
+    submounts.dedup();
+    submounts
+}
+
+// Spaces, tabs, newlines and backslashes are escaped as octal in the mount table.
+fn unescape_mount_path(path: &str) -> String {
+    path.replace("\\040", " ")
+        .replace("\\011", "\t")
+        .replace("\\012", "\n")
+        .replace("\\134", "\\")
+}
+
+// Share each submount of the volume and let the agent bind mount it at the same place
+// under the volume in the guest.
+async fn share_submounts(
+    share_fs_mount: &dyn ShareFsMount,
+    cid: &str,
+    source: &Path,
+    submounts: &[PathBuf],
+    guest_path: &str,
+    options: &[String],
+) -> Result<Vec<agent::Storage>> {
+    let mut storages = vec![];
+    let storage_options = bind_storage_options("bind", options);
+    for submount in submounts {
+        let file_name = submount
+            .file_name()
+            .and_then(|f| f.to_str())
+            .ok_or_else(|| anyhow!("invalid submount {:?}", submount))?;
+        let submount_source = submount
+            .to_str()
+            .ok_or_else(|| anyhow!("invalid submount {:?}", submount))?;
+        let relative = submount
+            .strip_prefix(source)
+            .with_context(|| format!("submount {:?} is not under {:?}", submount, source))?;
+
+        let mount_result = share_fs_mount
+            .share_volume(ShareFsVolumeConfig {
+                cid: cid.to_string(),
+                source: submount_source.to_string(),
+                target: generate_mount_path(cid, file_name).context("generate mount path")?,
+                readonly: MountOptions::parse(options).readonly,
+                mount_options: options.to_vec(),
+                mount: oci::Mount {
+                    destination: String::new(),
+                    r#type: "bind".to_string(),
+                    source: submount_source.to_string(),
+                    options: options.to_vec(),
+                },
+            })
+            .await
+            .with_context(|| format!("share submount {:?}", submount))?;
+
+        storages.push(agent::Storage {
+            driver: String::from(KATA_VIRTIO_FS_DEV_TYPE),
+            driver_options: Vec::new(),
+            source: mount_result.guest_path,
+            fs_type: String::from("bind"),
+            fs_group: None,
+            options: storage_options.clone(),
+            mount_point: Path::new(guest_path)
+                .join(relative)
+                .to_string_lossy()
+                .to_string(),
+        });
+    }
+    Ok(storages)
+}
+
+// The submounts are mounted under the volume, the container has to mount it recursively.
+fn set_recursive_bind(options: &mut Vec<String>) {
+    if let Some(o) = options.iter_mut().find(|o| *o == "bind") {
+        *o = String::from("rbind");
+    } else if !options.iter().any(|o| o == "rbind") {
+        options.push(String::from("rbind"));
+    }
+}
+
 // Note, don't generate random name, attaching rafs depends on the predictable name.
-pub fn generate_mount_path(id: &str, file_name: &str) -> String {
+pub fn generate_mount_path(id: &str, file_name: &str) -> Result<String> {
+    validate_path_component(file_name)?;
     let mut nid = String::from(id);
     if nid.len() > 10 {
         nid = nid.chars().take(10).collect();
     }
+    validate_path_component(&nid)?;
 
     let mut uid = uuid::Uuid::new_v4().to_string();
     let uid_vec: Vec<&str> = uid.splitn(2, '-').collect();
     uid = String::from(uid_vec[0]);
 
-    format!("{}-{}-{}", nid, uid, file_name)
+    Ok(format!("{}-{}-{}", nid, uid, file_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::share_fs::{ShareFsMountResult, ShareFsRootfsConfig};
+    use hypervisor::Hypervisor;
+
+    #[test]
+    fn test_generate_mount_path() {
+        let path = generate_mount_path("0123456789abcdef", "config.json").unwrap();
+        assert!(path.starts_with("0123456789-"));
+        assert!(path.ends_with("-config.json"));
+        assert!(!path.contains('/'));
+
+        for name in ["", ".", "..", "../../etc/passwd", "a/b"] {
+            assert!(generate_mount_path("cid", name).is_err(), "{:?}", name);
+        }
+        assert!(generate_mount_path("../cid", "config.json").is_err());
+    }
+
+    #[test]
+    fn test_guest_prefix_storage() {
+        let m = oci::Mount {
+            destination: "/var/lib/data".to_string(),
+            r#type: "bind".to_string(),
+            source: "/host/data".to_string(),
+            options: vec!["rbind".to_string(), "ro".to_string(), "nosuid".to_string()],
+        };
+        let guest_path = "/run/kata-containers/shared/containers/cid-abc-data";
+
+        assert!(guest_prefix_storage("", "cid", &m, guest_path)
+            .unwrap()
+            .is_none());
+
+        let storage = guest_prefix_storage("/run/kata/volumes", "cid", &m, guest_path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(storage.source, guest_path);
+        assert_eq!(storage.fs_type, "bind");
+        assert_eq!(storage.mount_point, "/run/kata/volumes/cid/var/lib/data");
+        assert_eq!(storage.options, vec!["rbind", "ro", "nosuid"]);
+
+        let m = oci::Mount {
+            destination: "/data/../../etc".to_string(),
+            ..m
+        };
+        assert!(guest_prefix_storage("/run/kata/volumes", "cid", &m, guest_path).is_err());
+
+        let m = oci::Mount {
+            destination: "/data".to_string(),
+            options: vec![],
+            ..m
+        };
+        let storage = guest_prefix_storage("/run/kata/volumes", "cid", &m, guest_path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(storage.options, vec!["rbind"]);
+        assert!(storage.driver_options.is_empty());
+    }
+
+    #[test]
+    fn test_bind_storage_options() {
+        assert_eq!(bind_storage_options("bind", &[]), vec!["bind"]);
+        assert_eq!(
+            bind_storage_options("rbind", &["rw".to_string(), "rprivate".to_string()]),
+            vec!["rbind"]
+        );
+        assert_eq!(
+            bind_storage_options("bind", &["nodev".to_string(), "ro".to_string()]),
+            vec!["bind", "ro", "nodev"]
+        );
+    }
+
+    struct MockShareFsMount;
+
+    struct MockShareFs;
+
+    #[async_trait::async_trait]
+    impl ShareFs for MockShareFs {
+        fn get_share_fs_mount(&self) -> Arc<dyn ShareFsMount> {
+            Arc::new(MockShareFsMount)
+        }
+        async fn setup_device_before_start_vm(&self, _h: &dyn Hypervisor) -> Result<()> {
+            Ok(())
+        }
+        async fn setup_device_after_start_vm(&self, _h: &dyn Hypervisor) -> Result<()> {
+            Ok(())
+        }
+        async fn get_storages(&self) -> Result<Vec<agent::Storage>> {
+            Ok(vec![])
+        }
+        async fn teardown(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ShareFsMount for MockShareFsMount {
+        async fn share_rootfs(&self, _config: ShareFsRootfsConfig) -> Result<ShareFsMountResult> {
+            Err(anyhow!("share rootfs is not supported"))
+        }
+
+        async fn share_volume(&self, config: ShareFsVolumeConfig) -> Result<ShareFsMountResult> {
+            Ok(ShareFsMountResult {
+                guest_path: format!("/run/kata-containers/shared/containers/{}", config.target),
+                storages: vec![],
+            })
+        }
+    }
+
+    const MOUNTINFO: &str = "\
+22 1 253:0 / / rw,relatime shared:1 - ext4 /dev/vda1 rw
+40 22 0:35 / /data rw,relatime shared:20 - ext4 /dev/vdb rw
+41 40 0:36 / /data/cache rw,relatime shared:21 - tmpfs tmpfs rw
+42 41 0:37 / /data/cache/my\\040dir rw,relatime shared:22 - tmpfs tmpfs rw
+43 22 0:38 / /database rw,relatime shared:23 - tmpfs tmpfs rw
+";
+
+    #[test]
+    fn test_parse_submounts() {
+        assert_eq!(
+            parse_submounts(MOUNTINFO, Path::new("/data")),
+            vec![
+                PathBuf::from("/data/cache"),
+                PathBuf::from("/data/cache/my dir")
+            ]
+        );
+        assert!(parse_submounts(MOUNTINFO, Path::new("/database")).is_empty());
+    }
+
+    #[test]
+    fn test_take_submounts_option() {
+        let options = vec![
+            "rbind".to_string(),
+            "kata.virtiofs.submounts=true".to_string(),
+        ];
+        let (submounts, rest) = take_submounts_option(&options).unwrap();
+        assert!(submounts);
+        assert_eq!(rest, vec!["rbind".to_string()]);
+
+        let (submounts, rest) = take_submounts_option(&["ro".to_string()]).unwrap();
+        assert!(!submounts);
+        assert_eq!(rest, vec!["ro".to_string()]);
+
+        assert!(take_submounts_option(&["kata.virtiofs.submounts=yes".to_string()]).is_err());
+
+        let mut options = vec!["bind".to_string(), "ro".to_string()];
+        set_recursive_bind(&mut options);
+        assert_eq!(options, vec!["rbind".to_string(), "ro".to_string()]);
+        let mut options = vec!["ro".to_string()];
+        set_recursive_bind(&mut options);
+        assert_eq!(options, vec!["ro".to_string(), "rbind".to_string()]);
+    }
+
+    #[actix_rt::test]
+    async fn test_share_submounts() {
+        let source = Path::new("/data");
+        let submounts = parse_submounts(MOUNTINFO, source);
+        let guest_path = "/run/kata-containers/shared/containers/cid-1234-data";
+        let storages = share_submounts(
+            &MockShareFsMount,
+            "cid",
+            source,
+            &submounts,
+            guest_path,
+            &["ro".to_string()],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(storages.len(), 2);
+        assert!(storages[0]
+            .source
+            .starts_with("/run/kata-containers/shared/containers/cid-"));
+        assert!(storages[0].source.ends_with("-cache"));
+        assert_eq!(storages[0].fs_type, "bind");
+        assert_eq!(storages[0].options, vec!["bind", "ro"]);
+        assert_eq!(storages[0].mount_point, format!("{}/cache", guest_path));
+        assert!(storages[1].source.ends_with("-my dir"));
+        assert_eq!(
+            storages[1].mount_point,
+            format!("{}/cache/my dir", guest_path)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_volume_flags_on_container_mount() {
+        let share_fs: Option<Arc<dyn ShareFs>> = Some(Arc::new(MockShareFs));
+        let m = oci::Mount {
+            destination: "/data".to_string(),
+            r#type: "bind".to_string(),
+            source: "/host/data".to_string(),
+            options: ["rbind", "ro", "nosuid", "nodev", "noexec"]
+                .iter()
+                .map(|o| o.to_string())
+                .collect(),
+        };
+
+        // the container's bind mount of the volume is remounted with its options,
+        // that's where the flags are applied, the guest prefix storage is only bound
+        let volume = ShareFsVolume::new(&share_fs, &m, "cid", "/run/kata/volumes")
+            .await
+            .unwrap();
+        let mounts = volume.get_volume_mount().unwrap();
+        assert_eq!(mounts.len(), 1);
+        assert_eq!(mounts[0].destination, "/data");
+        assert_eq!(mounts[0].source, "/run/kata/volumes/cid/data");
+        assert_eq!(mounts[0].options, m.options);
+
+        let storages = volume.get_storage().unwrap();
+        assert_eq!(storages.len(), 1);
+        assert_eq!(storages[0].fs_type, "bind");
+    }
 }
@@ -6,9 +6,9 @@
 
 use std::path::Path;
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 
-use super::Volume;
+use super::{MountOptions, Volume, VolumeKind};
 use crate::share_fs::DEFAULT_KATA_GUEST_SANDBOX_DIR;
 
 pub const SHM_DIR: &str = "shm";
@@ -27,7 +27,9 @@ pub(crate) struct ShmVolume {
 impl ShmVolume {
     pub(crate) fn new(m: &oci::Mount, shm_size: u64) -> Result<Self> {
         let (storage, mount) = if shm_size > 0 {
-            // storage
+            // storage, it's mounted at a sandbox wide path that all containers
+            // bind, the agent doesn't mount it again for later containers so
+            // the size of the first one is kept
             let mount_path = Path::new(DEFAULT_KATA_GUEST_SANDBOX_DIR).join(SHM_DIR);
             let mount_path = mount_path.to_str().unwrap();
             let option = format!("size={}", shm_size);
@@ -99,8 +101,77 @@ impl Volume for ShmVolume {
     fn cleanup(&self) -> Result<()> {
         todo!()
     }
+
+    fn kind(&self) -> VolumeKind {
+        VolumeKind::Shm
+    }
+
+    fn needs_agent_mount(&self) -> bool {
+        self.storage.is_some()
+    }
+}
+
+// get_shm_size returns the size in bytes requested by the `size=` option of the
+// /dev/shm mount, or DEFAULT_SHM_SIZE if there's none. Sizes can have a k, m or g
+// suffix as for tmpfs.
+pub(crate) fn get_shm_size(m: &oci::Mount) -> Result<u64> {
+    let options = MountOptions::parse(&m.options);
+    let size = match options.get("size") {
+        Some(size) => size,
+        None => return Ok(DEFAULT_SHM_SIZE),
+    };
+
+    let (num, shift) = match size.chars().last() {
+        Some('k') | Some('K') => (&size[..size.len() - 1], 10),
+        Some('m') | Some('M') => (&size[..size.len() - 1], 20),
+        Some('g') | Some('G') => (&size[..size.len() - 1], 30),
+        _ => (size, 0),
+    };
+    let num: u64 = num
+        .parse()
+        .with_context(|| format!("invalid shm size {:?}", size))?;
+    num.checked_mul(1 << shift)
+        .ok_or_else(|| anyhow!("shm size {:?} overflows", size))
 }
 
 pub(crate) fn is_shim_volume(m: &oci::Mount) -> bool {
     m.destination == "/dev/shm" && m.r#type != KATA_EPHEMERAL_DEV_TYPE
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_shm_mount(options: &[&str]) -> oci::Mount {
+        oci::Mount {
+            destination: "/dev/shm".to_string(),
+            r#type: "tmpfs".to_string(),
+            source: "shm".to_string(),
+            options: options.iter().map(|o| o.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_get_shm_size() {
+        let cases = [
+            (vec![], DEFAULT_SHM_SIZE),
+            (vec!["nosuid", "size=1024"], 1024),
+            (vec!["size=65536k"], 65536 * 1024),
+            (vec!["size=64M"], 64 << 20),
+            (vec!["size=2g", "mode=1777"], 2 << 30),
+        ];
+        for (options, size) in cases {
+            assert_eq!(get_shm_size(&new_shm_mount(&options)).unwrap(), size);
+        }
+
+        for size in [
+            "size=",
+            "size=50%",
+            "size=1t",
+            "size=-1",
+            "size=99999999999g",
+        ] {
+            assert!(get_shm_size(&new_shm_mount(&[size])).is_err(), "{}", size);
+        }
+    }
+}
@@ -17,7 +17,7 @@ use common::{
 };
 use kata_sys_util::k8s::update_ephemeral_storage_type;
 use oci::{LinuxResources, Process as OCIProcess};
-use resource::ResourceManager;
+use resource::{volume, ResourceManager};
 use tokio::sync::RwLock;
 
 use super::{
@@ -84,23 +84,29 @@ impl Container {
         amend_spec(&mut spec, toml_config.runtime.disable_guest_seccomp).context("amend spec")?;
         let sandbox_pidns = is_pid_namespace_enabled(&spec);
 
+        let root = spec
+            .root
+            .as_mut()
+            .ok_or_else(|| anyhow!("spec miss root field"))?;
+
         // handler rootfs
         let rootfs = self
             .resource_manager
-            .handler_rootfs(&config.container_id, &config.bundle, &config.rootfs_mounts)
+            .handler_rootfs(
+                &config.container_id,
+                root,
+                &config.bundle,
+                &config.rootfs_mounts,
+            )
             .await
             .context("handler rootfs")?;
 
         // update rootfs
-        match spec.root.as_mut() {
-            Some(spec) => {
-                spec.path = rootfs
-                    .get_guest_rootfs_path()
-                    .await
-                    .context("get guest rootfs path")?
-            }
-            None => return Err(anyhow!("spec miss root field")),
-        };
+        root.path = rootfs
+            .get_guest_rootfs_path()
+            .await
+            .context("get guest rootfs path")?;
+        let mut storages = rootfs.get_storage().await.context("get rootfs storage")?;
         inner.rootfs.push(rootfs);
 
         // handler volumes
@@ -110,18 +116,15 @@ impl Container {
             .await
             .context("handler volumes")?;
         let mut oci_mounts = vec![];
-        let mut storages = vec![];
 
+        storages.append(
+            &mut volume::VolumeResource::get_storages(&volumes).context("get volume storages")?,
+        );
         for v in volumes {
             let mut volume_mounts = v.get_volume_mount().context("get volume mount")?;
             if !volume_mounts.is_empty() {
                 oci_mounts.append(&mut volume_mounts);
             }
-
-            let mut s = v.get_storage().context("get storage")?;
-            if !s.is_empty() {
-                storages.append(&mut s);
-            }
             inner.volumes.push(v);
         }
         spec.mounts = oci_mounts;